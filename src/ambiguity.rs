@@ -0,0 +1,126 @@
+use crate::{storage::Storage, TypeSet};
+use std::{
+    any::{type_name, TypeId},
+    collections::{btree_map, BTreeMap, BTreeSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+type Hook = Box<dyn Fn(&'static str) + Send + Sync>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Whether a hook is set, so that misses are not checked when nothing would be reported
+static HOOKED: AtomicBool = AtomicBool::new(false);
+
+/// Register a function to be called with the type name whenever a lookup misses, but a different
+/// type with the same name is stored.
+///
+/// This usually means that more than one version of a crate is in the dependency graph. There is
+/// one hook for the whole process, and setting it replaces any previous hook. The hook must not
+/// call [`set_hook`] or [`clear_hook`].
+///
+/// Checking a miss scans every stored type, so misses are only checked while a hook is set. With
+/// the `log` feature, a warning is also logged for each ambiguous miss, and a hook that does
+/// nothing can be set to get only the warnings.
+///
+/// ## Example
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// let warnings = Arc::new(Mutex::new(Vec::new()));
+/// type_set::ambiguity::set_hook({
+///     let warnings = Arc::clone(&warnings);
+///     move |name| warnings.lock().unwrap().push(name)
+/// });
+///
+/// fn contains<T: Send + Sync + 'static>(set: &type_set::TypeSet, _: &T) -> bool {
+///     set.contains::<T>()
+/// }
+///
+/// // two closures have different types with the same name
+/// let (a, b) = (|| 1, || 2);
+/// let set = type_set::TypeSet::new().with(a);
+/// assert!(!contains(&set, &b));
+/// assert!(warnings.lock().unwrap()[0].ends_with("{{closure}}"));
+/// # type_set::ambiguity::clear_hook();
+/// ```
+pub fn set_hook(hook: impl Fn(&'static str) + Send + Sync + 'static) {
+    *HOOK.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(hook));
+    HOOKED.store(true, Ordering::Relaxed);
+}
+
+/// Remove the hook registered with [`set_hook`]
+pub fn clear_hook() {
+    HOOKED.store(false, Ordering::Relaxed);
+    *HOOK.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+#[cold]
+fn warn(name: &'static str) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        "{name} was not found in type-set, but a different type with the same name is present. \
+         There may be more than one version of its crate in the dependency graph."
+    );
+
+    if let Some(hook) = &*HOOK.read().unwrap_or_else(PoisonError::into_inner) {
+        hook(name);
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Whether a value of a different type with the same name as T is stored.
+    ///
+    /// Keyed and multi values are named after their key, so the type that each name was taken
+    /// from is compared rather than the stored value's own type.
+    pub(crate) fn shadows<T: 'static>(&self) -> bool {
+        let name = type_name::<T>();
+        self.values
+            .values()
+            .any(|value| value.name == name && (value.named)() != TypeId::of::<T>())
+    }
+
+    /// Report a lookup of T that found nothing, if a different type with the same name is stored
+    pub(crate) fn check_miss<T: 'static>(&self) {
+        if HOOKED.load(Ordering::Relaxed) && self.shadows::<T>() {
+            warn(type_name::<T>());
+        }
+    }
+
+    /// Returns the sorted names that are shared by more than one stored type.
+    ///
+    /// A lookup through one of these types will not find a value that was inserted through the
+    /// other, which usually means that more than one version of a crate is in the dependency
+    /// graph. See [`set_hook`] to be notified when a lookup misses for this reason.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// // two closures have different types with the same name
+    /// let set = type_set::TypeSet::new().with(|| 1).with(|| 2).with(1usize);
+    /// let ambiguous = set.ambiguous_types();
+    /// assert_eq!(ambiguous.len(), 1);
+    /// assert!(ambiguous[0].ends_with("{{closure}}"));
+    /// ```
+    #[must_use]
+    pub fn ambiguous_types(&self) -> Vec<&'static str> {
+        let mut type_ids = BTreeMap::<&'static str, TypeId>::new();
+        let mut ambiguous = BTreeSet::new();
+        for value in self.values.values() {
+            match type_ids.entry(value.name) {
+                btree_map::Entry::Vacant(vacant) => {
+                    vacant.insert((value.named)());
+                }
+                btree_map::Entry::Occupied(occupied) => {
+                    if *occupied.get() != (value.named)() {
+                        ambiguous.insert(value.name);
+                    }
+                }
+            }
+        }
+        ambiguous.into_iter().collect()
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{key, keyed, multi, storage::Storage, Key, TypeSet};
+use std::{
+    any::{type_name, TypeId},
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::{Mutex, PoisonError},
+};
+
+/// The operations counted by an [`AccessReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Access {
+    /// [`TypeSet::contains`] and [`TypeSet::contains_key`]
+    Contains,
+
+    /// [`TypeSet::get`], [`TypeSet::try_get`], [`TypeSet::get_key`], and [`TypeSet::get_all`]
+    Get,
+
+    /// [`TypeSet::get_mut`], [`TypeSet::try_get_mut`], and [`TypeSet::get_key_mut`]
+    GetMut,
+
+    /// [`TypeSet::take`], [`TypeSet::try_take`], [`TypeSet::take_key`], and
+    /// [`TypeSet::drain_all`]
+    Take,
+
+    /// [`TypeSet::entry`], [`TypeSet::entry_key`], [`TypeSet::entry_all`], and the
+    /// `get_or_insert` methods, which hit if the entry was occupied
+    Entry,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Access::Contains => "contains",
+            Access::Get => "get",
+            Access::GetMut => "get_mut",
+            Access::Take => "take",
+            Access::Entry => "entry",
+        })
+    }
+}
+
+/// The number of times an operation found a value, and the number of times it did not
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Counts {
+    hits: u64,
+    misses: u64,
+}
+
+impl Counts {
+    /// The number of times a value was present
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// The number of times no value was present
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+type Table = BTreeMap<(Key, Access), (&'static str, Counts)>;
+
+pub(crate) struct Audit(Option<Mutex<Table>>);
+
+impl Audit {
+    pub(crate) const fn new() -> Self {
+        Self(None)
+    }
+
+    fn enable(&mut self) {
+        if self.0.is_none() {
+            self.0 = Some(Mutex::new(BTreeMap::new()));
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    fn report(&self) -> AccessReport {
+        let Some(table) = &self.0 else {
+            return AccessReport::default();
+        };
+
+        AccessReport(
+            table
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(&(key, access), &(name, counts))| ((name, key, access), counts))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn record<T: 'static>(&self, access: Access, hit: bool) {
+        self.record_key(key::<T>(), type_name::<T>(), access, hit);
+    }
+
+    /// Record an access to the slot `key`, which is reported under `name`
+    pub(crate) fn record_key(&self, key: Key, name: &'static str, access: Access, hit: bool) {
+        let Some(table) = &self.0 else {
+            return;
+        };
+
+        let mut table = table.lock().unwrap_or_else(PoisonError::into_inner);
+        let (_, counts) = table
+            .entry((key, access))
+            .or_insert_with(|| (name, Counts::default()));
+        if hit {
+            counts.hits += 1;
+        } else {
+            counts.misses += 1;
+        }
+    }
+}
+
+/// The accesses recorded by a `TypeSet` with auditing enabled
+///
+/// This type is constructed by [`TypeSet::access_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessReport(BTreeMap<(&'static str, TypeId, Access), Counts>);
+
+impl AccessReport {
+    /// The counts of an operation on type T, which are zero if it was never recorded
+    #[must_use]
+    pub fn counts<T: 'static>(&self, access: Access) -> Counts {
+        self.0
+            .get(&(type_name::<T>(), key::<T>(), access))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The counts of an operation on key K, which are zero if it was never recorded. See
+    /// [`TypeSet::get_key`].
+    #[must_use]
+    pub fn counts_key<K: keyed::Key>(&self, access: Access) -> Counts {
+        self.0
+            .get(&(type_name::<K>(), keyed::key_of::<K>(), access))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The counts of an operation on the [pushed][TypeSet::push] T values, which are zero if it
+    /// was never recorded. See [`TypeSet::get_all`].
+    #[must_use]
+    pub fn counts_all<T: 'static>(&self, access: Access) -> Counts {
+        self.counts_key::<multi::All<T>>(access)
+    }
+
+    /// Iterate over every recorded operation, sorted by type name and then by operation
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Access, Counts)> + '_ {
+        self.0
+            .iter()
+            .map(|(&(name, _, access), &counts)| (name, access, counts))
+    }
+
+    /// Returns true if no operation was recorded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Lists each type and operation on its own line, with its hits and misses.
+impl Display for AccessReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (name, access, counts) in self.iter() {
+            if !first {
+                f.write_str("\n")?;
+            }
+            first = false;
+            write!(
+                f,
+                "{name} {access}: {} hits, {} misses",
+                counts.hits, counts.misses
+            )?;
+        }
+
+        if first {
+            f.write_str("no accesses")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Start counting the operations on each type in this `TypeSet`.
+    ///
+    /// Each call to [`contains`][TypeSet::contains], [`get`][TypeSet::get],
+    /// [`get_mut`][TypeSet::get_mut], [`take`][TypeSet::take], and [`entry`][TypeSet::entry] is
+    /// recorded as a hit or a miss, and can be inspected with [`TypeSet::access_report`]. The
+    /// keyed and multi forms of these methods are recorded separately, and can be inspected with
+    /// [`AccessReport::counts_key`] and [`AccessReport::counts_all`]. Auditing is not carried
+    /// over when cloning, converting, or partitioning a `TypeSet`. Calling this again has no
+    /// effect.
+    pub fn enable_auditing(&mut self) {
+        self.audit.enable();
+    }
+
+    /// Chainable form of [`TypeSet::enable_auditing`]
+    #[must_use]
+    pub fn with_auditing(mut self) -> Self {
+        self.enable_auditing();
+        self
+    }
+
+    /// Returns true if [auditing][TypeSet::enable_auditing] is enabled
+    #[must_use]
+    pub fn is_auditing(&self) -> bool {
+        self.audit.is_enabled()
+    }
+
+    /// Returns the operations recorded since auditing was enabled.
+    ///
+    /// The report is empty if [auditing][TypeSet::enable_auditing] is not enabled.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use type_set::audit::Access;
+    /// let mut set = type_set::TypeSet::new().with(1usize).with_auditing();
+    /// assert_eq!(set.get::<usize>(), Some(&1));
+    /// assert_eq!(set.get::<usize>(), Some(&1));
+    /// assert_eq!(set.get::<u8>(), None);
+    /// set.entry::<u8>().or_insert(8);
+    /// let report = set.access_report();
+    /// assert_eq!(report.counts::<usize>(Access::Get).hits(), 2);
+    /// assert_eq!(report.counts::<u8>(Access::Get).misses(), 1);
+    /// assert_eq!(
+    ///     report.to_string(),
+    ///     "u8 get: 0 hits, 1 misses\nu8 entry: 0 hits, 1 misses\nusize get: 2 hits, 0 misses"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn access_report(&self) -> AccessReport {
+        self.audit.report()
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    storage::sealed::{self, Promote},
+    unwrap,
+};
+use std::{
+    any::{type_name, Any, TypeId},
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+type CloneFn = fn(&dyn Any) -> Box<dyn Any>;
+type DebugFn = fn(&dyn Any, &mut Formatter<'_>) -> fmt::Result;
+type EqFn = fn(&dyn Any, &dyn Any) -> bool;
+type HashFn = fn(&dyn Any, &mut dyn Hasher);
+
+/// Functions for a stored type that were captured while its concrete type was still known
+#[derive(Clone, Default)]
+pub(crate) struct Vtable {
+    pub(crate) clone: Option<CloneFn>,
+    pub(crate) debug: Option<DebugFn>,
+    pub(crate) eq: Option<EqFn>,
+    pub(crate) hash: Option<HashFn>,
+    pub(crate) casts: Vec<Cast>,
+}
+
+/// A recorded conversion from a stored value to a trait object U, erased so that it can be held
+/// without naming U
+#[derive(Clone)]
+pub(crate) struct Cast {
+    target: TypeId,
+    name: &'static str,
+    caster: Arc<dyn Any + Send + Sync>,
+}
+
+type Caster<U> = Box<dyn Fn(&dyn Any) -> &U + Send + Sync>;
+
+impl Vtable {
+    pub(crate) fn cast<'a, U: ?Sized + 'static>(&self, any: &'a dyn Any) -> Option<&'a U> {
+        let cast = self
+            .casts
+            .iter()
+            .find(|cast| cast.target == TypeId::of::<U>())?;
+        Some(unwrap!(cast.caster.downcast_ref::<Caster<U>>())(any))
+    }
+}
+
+fn clone_any<T: Clone + 'static>(any: &dyn Any) -> Box<dyn Any> {
+    Box::new(unwrap!(any.downcast_ref::<T>()).clone())
+}
+
+fn eq_any<T: PartialEq + 'static>(a: &dyn Any, b: &dyn Any) -> bool {
+    unwrap!(a.downcast_ref::<T>()) == unwrap!(b.downcast_ref::<T>())
+}
+
+fn hash_any<T: Hash + 'static>(any: &dyn Any, mut state: &mut dyn Hasher) {
+    unwrap!(any.downcast_ref::<T>()).hash(&mut state);
+}
+
+fn debug_any<T: Debug + 'static>(any: &dyn Any, f: &mut Formatter<'_>) -> fmt::Result {
+    Debug::fmt(unwrap!(any.downcast_ref::<T>()), f)
+}
+
+/// A value to be inserted into a [`TypeSet`][crate::TypeSet], along with any capabilities that
+/// should be recorded for it.
+///
+/// Because a `TypeSet` erases the types it stores, traits like [`Clone`] and [`Debug`] can only be
+/// used on stored values if they are captured at insertion. See
+/// [`TypeSet::insert_captured`][crate::TypeSet::insert_captured].
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::{capture::Capture, TypeSet};
+/// let set = TypeSet::new().with_captured(Capture::new(10usize).cloneable().debug());
+/// assert_eq!(format!("{:?}", set.clone()), r#"TypeSet({"usize" => 10})"#);
+/// ```
+pub struct Capture<T> {
+    pub(crate) value: T,
+    pub(crate) vtable: Vtable,
+    pub(crate) promote: Promote,
+}
+
+impl<T: Debug> Debug for Capture<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture")
+            .field("value", &self.value)
+            .field("cloneable", &self.vtable.clone.is_some())
+            .field("debug", &self.vtable.debug.is_some())
+            .field("comparable", &self.vtable.eq.is_some())
+            .field("hashable", &self.vtable.hash.is_some())
+            .field("send", &self.promote.send.is_some())
+            .field("send_sync", &self.promote.send_sync.is_some())
+            .field(
+                "casts",
+                &self
+                    .vtable
+                    .casts
+                    .iter()
+                    .map(|cast| cast.name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<T: 'static> Capture<T> {
+    /// Prepare a value for insertion without any capabilities
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            vtable: Vtable::default(),
+            promote: Promote::default(),
+        }
+    }
+
+    /// Record how to clone this value, allowing the `TypeSet` to be cloned
+    #[must_use]
+    pub fn cloneable(mut self) -> Self
+    where
+        T: Clone,
+    {
+        self.vtable.clone = Some(clone_any::<T>);
+        self
+    }
+
+    /// Record how to format this value, allowing it to be shown in the `TypeSet`'s [`Debug`]
+    /// output
+    #[must_use]
+    pub fn debug(mut self) -> Self
+    where
+        T: Debug,
+    {
+        self.vtable.debug = Some(debug_any::<T>);
+        self
+    }
+
+    /// Record how to compare this value for equality, allowing the `TypeSet` to be compared with
+    /// [`PartialEq`] and changes to it to be found with [`TypeSet::diff`][crate::TypeSet::diff]
+    #[must_use]
+    pub fn comparable(mut self) -> Self
+    where
+        T: PartialEq,
+    {
+        self.vtable.eq = Some(eq_any::<T>);
+        self
+    }
+
+    /// Record how to hash this value, allowing the `TypeSet` to be hashed with
+    /// [`TypeSet::try_hash`][crate::TypeSet::try_hash]
+    #[must_use]
+    pub fn hashable(mut self) -> Self
+    where
+        T: Hash,
+    {
+        self.vtable.hash = Some(hash_any::<T>);
+        self
+    }
+
+    /// Record that this value is [`Send`], allowing a
+    /// [`LocalTypeSet`][crate::LocalTypeSet] that contains it to be converted into a
+    /// [`SendTypeSet`][crate::SendTypeSet]
+    #[must_use]
+    pub fn send(mut self) -> Self
+    where
+        T: Send,
+    {
+        self.promote.send = Some(sealed::send::<T>);
+        self
+    }
+
+    /// Record that this value is [`Send`] and [`Sync`], allowing a
+    /// [`LocalTypeSet`][crate::LocalTypeSet] or [`SendTypeSet`][crate::SendTypeSet] that
+    /// contains it to be converted into a [`TypeSet`][crate::TypeSet]
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::rc::Rc;
+    /// use type_set::{capture::Capture, storage::SendSync, LocalTypeSet, TypeSet};
+    /// let set = LocalTypeSet::default().with_captured(Capture::new("hello").send_sync());
+    /// let set: TypeSet = set.try_into().unwrap();
+    /// assert_eq!(set.get::<&'static str>(), Some(&"hello"));
+    ///
+    /// let set = LocalTypeSet::default().with(Rc::new(1)).with_captured(Capture::new(1u8).send());
+    /// assert!(TypeSet::<SendSync>::try_from(set).is_err());
+    /// ```
+    #[must_use]
+    pub fn send_sync(mut self) -> Self
+    where
+        T: Send + Sync,
+    {
+        self.promote.send = Some(sealed::send::<T>);
+        self.promote.send_sync = Some(sealed::send_sync::<T>);
+        self
+    }
+
+    /// Record how to view this value as the trait object U, allowing it to be found with
+    /// [`TypeSet::get_dyn`][crate::TypeSet::get_dyn] and
+    /// [`TypeSet::iter_dyn`][crate::TypeSet::iter_dyn].
+    ///
+    /// The `cast` function is usually the identity closure `|value| value`, which the compiler
+    /// coerces to the trait object. Any number of trait objects can be recorded.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::{any::TypeId, fmt::Display};
+    /// use type_set::{capture::Capture, TypeSet};
+    /// let set = TypeSet::new().with_captured(Capture::new(10usize).cast::<dyn Display>(|n| n));
+    /// let display = set.get_dyn::<dyn Display>(TypeId::of::<usize>()).unwrap();
+    /// assert_eq!(display.to_string(), "10");
+    /// ```
+    #[must_use]
+    pub fn cast<U: ?Sized + 'static>(mut self, cast: fn(&T) -> &U) -> Self {
+        let caster: Caster<U> = Box::new(move |any| cast(unwrap!(any.downcast_ref::<T>())));
+        self.vtable
+            .casts
+            .retain(|existing| existing.target != TypeId::of::<U>());
+        self.vtable.casts.push(Cast {
+            target: TypeId::of::<U>(),
+            name: type_name::<U>(),
+            caster: Arc::new(caster),
+        });
+        self
+    }
+
+    /// Returns the value, discarding any recorded capabilities
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
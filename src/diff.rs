@@ -0,0 +1,136 @@
+use crate::{storage::Storage, TypeSet};
+use std::fmt::{self, Display, Formatter};
+
+/// The differences between two [`TypeSet`]s, by type name
+///
+/// This type is constructed by [`TypeSet::diff`]. Each list of names is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    added: Vec<&'static str>,
+    removed: Vec<&'static str>,
+    changed: Vec<&'static str>,
+    unchecked: Vec<&'static str>,
+}
+
+impl Diff {
+    /// Types that are only present in `after`
+    #[must_use]
+    pub fn added(&self) -> &[&'static str] {
+        &self.added
+    }
+
+    /// Types that are only present in `before`
+    #[must_use]
+    pub fn removed(&self) -> &[&'static str] {
+        &self.removed
+    }
+
+    /// Types that are present in both, were captured as
+    /// [comparable][crate::capture::Capture::comparable], and are not equal
+    #[must_use]
+    pub fn changed(&self) -> &[&'static str] {
+        &self.changed
+    }
+
+    /// Types that are present in both but could not be compared, because neither value was
+    /// captured as [comparable][crate::capture::Capture::comparable]
+    #[must_use]
+    pub fn unchecked(&self) -> &[&'static str] {
+        &self.unchecked
+    }
+
+    /// Returns true if no type was added, removed, or changed
+    ///
+    /// Unchecked types may still differ.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Lists each type on its own line, prefixed with `+` if added, `-` if removed, `~` if changed,
+/// and `?` if unchecked.
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let lines = [
+            ('+', &self.added),
+            ('-', &self.removed),
+            ('~', &self.changed),
+            ('?', &self.unchecked),
+        ];
+
+        let mut first = true;
+        for (prefix, names) in lines {
+            for name in names {
+                if !first {
+                    f.write_str("\n")?;
+                }
+                first = false;
+                write!(f, "{prefix} {name}")?;
+            }
+        }
+
+        if first {
+            f.write_str("no changes")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Compare the types in two `TypeSet`s.
+    ///
+    /// Values that are present in both can only be compared if either was captured as
+    /// [comparable][crate::capture::Capture::comparable]. Otherwise they are reported as
+    /// [unchecked][Diff::unchecked].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use type_set::{capture::Capture, TypeSet};
+    /// let before = TypeSet::new()
+    ///     .with_captured(Capture::new(1usize).comparable())
+    ///     .with("removed");
+    /// let after = TypeSet::new()
+    ///     .with_captured(Capture::new(2usize).comparable())
+    ///     .with(3u8);
+    /// let diff = TypeSet::diff(&before, &after);
+    /// assert_eq!(diff.added(), ["u8"]);
+    /// assert_eq!(diff.removed(), ["&str"]);
+    /// assert_eq!(diff.changed(), ["usize"]);
+    /// assert_eq!(diff.to_string(), "+ u8\n- &str\n~ usize");
+    /// ```
+    #[must_use]
+    pub fn diff(before: &Self, after: &Self) -> Diff {
+        let mut diff = Diff::default();
+
+        for (key, value) in &before.values {
+            match after.values.get(key) {
+                None => diff.removed.push(value.name),
+                Some(other) => match value.eq(other) {
+                    Some(true) => {}
+                    Some(false) => diff.changed.push(value.name),
+                    None => diff.unchecked.push(value.name),
+                },
+            }
+        }
+
+        for (key, value) in &after.values {
+            if !before.values.contains_key(key) {
+                diff.added.push(value.name);
+            }
+        }
+
+        for names in [
+            &mut diff.added,
+            &mut diff.removed,
+            &mut diff.changed,
+            &mut diff.unchecked,
+        ] {
+            names.sort_unstable();
+        }
+
+        diff
+    }
+}
@@ -1,6 +1,10 @@
-use crate::{unwrap, Key, Value};
+use crate::{
+    observe::Observers,
+    storage::{SendSync, Storable, Storage},
+    unwrap, Key, Value,
+};
 use std::{
-    any::{type_name, Any, TypeId},
+    any::{type_name, TypeId},
     collections::btree_map,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
@@ -34,15 +38,15 @@ use std::{
 /// assert_eq!(previous, Some("hello"));
 /// assert_eq!(*current, "entry was occupied");
 /// ```
-pub enum Entry<'a, T> {
+pub enum Entry<'a, T, S: Storage = SendSync> {
     /// A view into the location a T would be stored in the `TypeSet`. See [`VacantEntry`]
-    Vacant(VacantEntry<'a, T>),
+    Vacant(VacantEntry<'a, T, S>),
 
     /// A view into the location a T is currently stored in the `TypeSet`. See [`OccupiedEntry`]
-    Occupied(OccupiedEntry<'a, T>),
+    Occupied(OccupiedEntry<'a, T, S>),
 }
 
-impl<'a, T: Debug + Any + Send + Sync + 'static> Debug for Entry<'a, T> {
+impl<T: Debug + Storable<S>, S: Storage> Debug for Entry<'_, T, S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::Vacant(vacant_entry) => f.debug_tuple("Vacant").field(vacant_entry).finish(),
@@ -53,26 +57,33 @@ impl<'a, T: Debug + Any + Send + Sync + 'static> Debug for Entry<'a, T> {
     }
 }
 
+/// Names a value inserted through an entry after the type or key that the entry was made for
+type Name<S> = fn(Value<S>) -> Value<S>;
+
 /// A view into a vacant entry in a `TypeSet`.
 ///
 /// It is part of the [`Entry`] enum.
-pub struct VacantEntry<'a, T>(
-    pub(super) btree_map::VacantEntry<'a, Key, Value>,
+pub struct VacantEntry<'a, T, S: Storage = SendSync>(
+    pub(super) btree_map::VacantEntry<'a, Key, Value<S>>,
+    &'a mut Observers<S>,
+    Name<S>,
     PhantomData<T>,
 );
 
-impl<'a, T: Debug + Any + Send + Sync + 'static> Debug for VacantEntry<'a, T> {
+impl<T: Debug + Storable<S>, S: Storage> Debug for VacantEntry<'_, T, S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "VacantEntry<{}>", type_name::<T>())
     }
 }
 /// A view into the location a T is stored
-pub struct OccupiedEntry<'a, T>(
-    pub(super) btree_map::OccupiedEntry<'a, Key, Value>,
+pub struct OccupiedEntry<'a, T, S: Storage = SendSync>(
+    pub(super) btree_map::OccupiedEntry<'a, Key, Value<S>>,
+    &'a mut Observers<S>,
+    Name<S>,
     PhantomData<T>,
 );
 
-impl<'a, T: Debug + Any + Send + Sync + 'static> Debug for OccupiedEntry<'a, T> {
+impl<T: Debug + Storable<S>, S: Storage> Debug for OccupiedEntry<'_, T, S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_tuple(&format!("OccupiedEntry<{}>", type_name::<T>()))
             .field(unwrap!(self.0.get().downcast_ref::<T>()))
@@ -80,7 +91,7 @@ impl<'a, T: Debug + Any + Send + Sync + 'static> Debug for OccupiedEntry<'a, T>
     }
 }
 
-impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
+impl<'a, T: Storable<S>, S: Storage> Entry<'a, T, S> {
     /// Ensures a value is in the `Entry` by inserting the provided `default` value if the Entry was
     /// previously vacant. Returns a mutable reference to the value.
     ///
@@ -95,6 +106,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
     /// assert_eq!(*set.entry().or_insert("world"), "hello");
     /// assert_eq!(set.get::<&'static str>(), Some(&"hello"));
     /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
     pub fn or_insert(self, default: T) -> &'a mut T {
         match self {
             Entry::Vacant(vacant) => vacant.insert(default),
@@ -116,6 +128,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
     /// assert_eq!(*set.entry::<String>().or_insert_with(|| panic!("never called")), "hello");
     /// assert_eq!(set.get::<String>(), Some(&String::from("hello")));
     /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
     pub fn or_insert_with(self, default: impl FnOnce() -> T) -> &'a mut T {
         match self {
             Entry::Vacant(vacant) => vacant.insert(default()),
@@ -164,7 +177,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
     ///
     /// This function will panic if the entry is vacant
     #[must_use]
-    pub fn unwrap_occupied(self) -> OccupiedEntry<'a, T> {
+    pub fn unwrap_occupied(self) -> OccupiedEntry<'a, T, S> {
         self.into_occupied().unwrap_or_else(|| {
             panic!(
                 "expected an occupied type-set entry for {}, but was vacant",
@@ -179,7 +192,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
     ///
     /// This function will panic if the entry is occupied
     #[must_use]
-    pub fn unwrap_vacant(self) -> VacantEntry<'a, T> {
+    pub fn unwrap_vacant(self) -> VacantEntry<'a, T, S> {
         self.into_vacant().unwrap_or_else(|| {
             panic!(
                 "expected a vacant type-set entry for {}, but was occupied",
@@ -196,7 +209,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
 
     /// Returns an [`OccupiedEntry`] or `None` if this entry is vacant.
     #[must_use]
-    pub fn into_occupied(self) -> Option<OccupiedEntry<'a, T>> {
+    pub fn into_occupied(self) -> Option<OccupiedEntry<'a, T, S>> {
         match self {
             Entry::Occupied(occupied_entry) => Some(occupied_entry),
             Entry::Vacant(_) => None,
@@ -205,7 +218,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
 
     /// Returns a [`VacantEntry`] or `None` if this entry is occupied.
     #[must_use]
-    pub fn into_vacant(self) -> Option<VacantEntry<'a, T>> {
+    pub fn into_vacant(self) -> Option<VacantEntry<'a, T, S>> {
         match self {
             Entry::Occupied(_) => None,
             Entry::Vacant(vacant_entry) => Some(vacant_entry),
@@ -222,6 +235,7 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
     ///
     /// If the Entry is already an [`OccupiedEntry`], the previously contained value will be
     /// returned
+    #[cfg_attr(feature = "provenance", track_caller)]
     pub fn insert(self, value: T) -> Option<T> {
         match self {
             Entry::Vacant(v) => {
@@ -239,17 +253,24 @@ impl<'a, T: Send + Sync + 'static> Entry<'a, T> {
         }
     }
 
-    pub(super) fn new(entry: btree_map::Entry<'a, TypeId, Value>) -> Self {
+    /// Build an entry whose inserted values are named after N, which is T or a key for T
+    pub(super) fn new<N: 'static>(
+        entry: btree_map::Entry<'a, TypeId, Value<S>>,
+        observers: &'a mut Observers<S>,
+    ) -> Self {
+        let name = Value::named::<N>;
         match entry {
-            btree_map::Entry::Vacant(vacant) => Self::Vacant(VacantEntry(vacant, PhantomData)),
+            btree_map::Entry::Vacant(vacant) => {
+                Self::Vacant(VacantEntry(vacant, observers, name, PhantomData))
+            }
             btree_map::Entry::Occupied(occupied) => {
-                Self::Occupied(OccupiedEntry(occupied, PhantomData))
+                Self::Occupied(OccupiedEntry(occupied, observers, name, PhantomData))
             }
         }
     }
 }
 
-impl<'a, T: Default + Send + Sync + 'static> Entry<'a, T> {
+impl<'a, T: Default + Storable<S>, S: Storage> Entry<'a, T, S> {
     /// Ensures a value is in the Entry by inserting the default value if vacant, and returns a
     /// mutable reference to the value.
     ///
@@ -263,6 +284,7 @@ impl<'a, T: Default + Send + Sync + 'static> Entry<'a, T> {
     /// set.insert("hello");
     /// assert_eq!(*set.entry::<&'static str>().or_default(), "hello");
     /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
     pub fn or_default(self) -> &'a mut T {
         #[allow(clippy::unwrap_or_default)]
         // this is the implementation of or_default so it can't call or_default
@@ -270,14 +292,18 @@ impl<'a, T: Default + Send + Sync + 'static> Entry<'a, T> {
     }
 }
 
-impl<'a, T: Send + Sync + 'static> VacantEntry<'a, T> {
+impl<'a, T: Storable<S>, S: Storage> VacantEntry<'a, T, S> {
     /// Sets the value of this entry to the provided `value`
+    #[cfg_attr(feature = "provenance", track_caller)]
     pub fn insert(self, value: T) -> &'a mut T {
-        unwrap!(self.0.insert(Value::new(value)).downcast_mut())
+        let key = *self.0.key();
+        let value = self.0.insert((self.2)(Value::new(value)));
+        self.1.notify(key, None, Some(value));
+        unwrap!(value.downcast_mut())
     }
 }
 
-impl<'a, T: Send + Sync + 'static> OccupiedEntry<'a, T> {
+impl<'a, T: Storable<S>, S: Storage> OccupiedEntry<'a, T, S> {
     /// Gets a reference to the value in this entry
     #[must_use]
     pub fn get(&self) -> &T {
@@ -294,14 +320,20 @@ impl<'a, T: Send + Sync + 'static> OccupiedEntry<'a, T> {
     }
 
     /// Sets the value of the entry to `value`, returning the entry's previous value.
+    #[cfg_attr(feature = "provenance", track_caller)]
     pub fn insert(&mut self, value: T) -> T {
-        unwrap!(self.0.insert(Value::new(value)).downcast())
+        let previous = self.0.insert((self.2)(Value::new(value)));
+        self.1
+            .notify(*self.0.key(), Some(&previous), Some(self.0.get()));
+        unwrap!(previous.downcast())
     }
 
     /// Take ownership of the value from this Entry
     #[allow(clippy::must_use_candidate)] // sometimes we just want to take the value out and drop it
     pub fn remove(self) -> T {
-        unwrap!(self.0.remove().downcast())
+        let (key, previous) = self.0.remove_entry();
+        self.1.notify(key, Some(&previous), None);
+        unwrap!(previous.downcast())
     }
 
     /// Converts the entry into a mutable reference to its value.
@@ -313,7 +345,7 @@ impl<'a, T: Send + Sync + 'static> OccupiedEntry<'a, T> {
     }
 }
 
-impl<'a, T: Send + Sync + 'static> Deref for OccupiedEntry<'a, T> {
+impl<T: Storable<S>, S: Storage> Deref for OccupiedEntry<'_, T, S> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -321,20 +353,20 @@ impl<'a, T: Send + Sync + 'static> Deref for OccupiedEntry<'a, T> {
     }
 }
 
-impl<'a, T: Send + Sync + 'static> DerefMut for OccupiedEntry<'a, T> {
+impl<T: Storable<S>, S: Storage> DerefMut for OccupiedEntry<'_, T, S> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.get_mut()
     }
 }
 
-impl<'a, T: Send + Sync + 'static> From<OccupiedEntry<'a, T>> for Entry<'a, T> {
-    fn from(occupied_entry: OccupiedEntry<'a, T>) -> Self {
+impl<'a, T: Storable<S>, S: Storage> From<OccupiedEntry<'a, T, S>> for Entry<'a, T, S> {
+    fn from(occupied_entry: OccupiedEntry<'a, T, S>) -> Self {
         Self::Occupied(occupied_entry)
     }
 }
 
-impl<'a, T: Send + Sync + 'static> From<VacantEntry<'a, T>> for Entry<'a, T> {
-    fn from(vacant_entry: VacantEntry<'a, T>) -> Self {
+impl<'a, T: Storable<S>, S: Storage> From<VacantEntry<'a, T, S>> for Entry<'a, T, S> {
+    fn from(vacant_entry: VacantEntry<'a, T, S>) -> Self {
         Self::Vacant(vacant_entry)
     }
 }
@@ -0,0 +1,357 @@
+use crate::{
+    capture::Capture,
+    debug_values, key,
+    storage::{SendSync, Storable, Storage},
+    unwrap, Key, TypeSet, Value,
+};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+type Link<S> = Option<Arc<Node<S>>>;
+
+/// A node of a treap: a search tree by key that is also a heap by a priority derived from the key.
+///
+/// Every set of keys has one shape, which is balanced on average. Nodes are never modified once
+/// they are built, so an update copies only the path from the root to the changed key.
+struct Node<S: Storage> {
+    key: Key,
+    priority: u64,
+    value: Arc<Value<S>>,
+    left: Link<S>,
+    right: Link<S>,
+}
+
+impl<S: Storage> Node<S> {
+    fn new(key: Key, value: Arc<Value<S>>, left: Link<S>, right: Link<S>) -> Arc<Self> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        Arc::new(Self {
+            key,
+            priority: hasher.finish(),
+            value,
+            left,
+            right,
+        })
+    }
+
+    /// Ties between priorities are broken by key, so that no two nodes have the same rank
+    fn rank(&self) -> (u64, Key) {
+        (self.priority, self.key)
+    }
+
+    fn with_children(&self, left: Link<S>, right: Link<S>) -> Arc<Self> {
+        Arc::new(Self {
+            value: Arc::clone(&self.value),
+            left,
+            right,
+            ..*self
+        })
+    }
+}
+
+fn get<S: Storage>(mut link: Option<&Arc<Node<S>>>, key: Key) -> Option<&Arc<Value<S>>> {
+    while let Some(node) = link {
+        link = match key.cmp(&node.key) {
+            Ordering::Less => node.left.as_ref(),
+            Ordering::Greater => node.right.as_ref(),
+            Ordering::Equal => return Some(&node.value),
+        };
+    }
+    None
+}
+
+/// Returns the tree with `new` in place of any node with the same key, and whether one was
+/// replaced
+fn insert<S: Storage>(link: Option<&Arc<Node<S>>>, new: Arc<Node<S>>) -> (Arc<Node<S>>, bool) {
+    let Some(node) = link else {
+        return (new, false);
+    };
+
+    if new.key == node.key {
+        let left = node.left.clone();
+        let right = node.right.clone();
+        return (new.with_children(left, right), true);
+    }
+
+    // every node below `node` ranks lower than it, so a higher-ranked key is not in this subtree
+    if new.rank() > node.rank() {
+        let (left, right) = split(link, new.key);
+        return (new.with_children(left, right), false);
+    }
+
+    if new.key < node.key {
+        let (left, replaced) = insert(node.left.as_ref(), new);
+        (node.with_children(Some(left), node.right.clone()), replaced)
+    } else {
+        let (right, replaced) = insert(node.right.as_ref(), new);
+        (node.with_children(node.left.clone(), Some(right)), replaced)
+    }
+}
+
+/// Split a tree that does not contain `key` into the keys below it and the keys above it
+fn split<S: Storage>(link: Option<&Arc<Node<S>>>, key: Key) -> (Link<S>, Link<S>) {
+    let Some(node) = link else {
+        return (None, None);
+    };
+
+    if key < node.key {
+        let (left, right) = split(node.left.as_ref(), key);
+        (left, Some(node.with_children(right, node.right.clone())))
+    } else {
+        let (left, right) = split(node.right.as_ref(), key);
+        (Some(node.with_children(node.left.clone(), left)), right)
+    }
+}
+
+/// Join two trees, where every key in `left` is below every key in `right`
+fn merge<S: Storage>(left: Option<&Arc<Node<S>>>, right: Option<&Arc<Node<S>>>) -> Link<S> {
+    match (left, right) {
+        (None, link) | (link, None) => link.cloned(),
+        (Some(l), Some(r)) => Some(if l.rank() > r.rank() {
+            l.with_children(l.left.clone(), merge(l.right.as_ref(), right))
+        } else {
+            r.with_children(merge(left, r.left.as_ref()), r.right.clone())
+        }),
+    }
+}
+
+/// Returns the tree without `key`, or None if it was not present
+fn remove<S: Storage>(link: Option<&Arc<Node<S>>>, key: Key) -> Option<Link<S>> {
+    let node = link?;
+    Some(match key.cmp(&node.key) {
+        Ordering::Less => {
+            Some(node.with_children(remove(node.left.as_ref(), key)?, node.right.clone()))
+        }
+        Ordering::Greater => {
+            Some(node.with_children(node.left.clone(), remove(node.right.as_ref(), key)?))
+        }
+        Ordering::Equal => merge(node.left.as_ref(), node.right.as_ref()),
+    })
+}
+
+fn collect<'a, S: Storage>(link: Option<&'a Arc<Node<S>>>, values: &mut Vec<&'a Value<S>>) {
+    if let Some(node) = link {
+        collect(node.left.as_ref(), values);
+        values.push(&node.value);
+        collect(node.right.as_ref(), values);
+    }
+}
+
+/// A persistent collection for heterogenous types.
+///
+/// Both the values and the table of types are held behind [`Arc`]s, so cloning an
+/// `ImmutableTypeSet` never copies anything, and [`with`][ImmutableTypeSet::with] and
+/// [`without`][ImmutableTypeSet::without] copy only the few table entries on the path to the
+/// changed type, which is logarithmic in the number of types on average. Every other value and
+/// table entry is shared with the original, so a set can be forked for each branch of a
+/// computation, or updated in a long chain, without cloning any stored value.
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::ImmutableTypeSet;
+/// let base = ImmutableTypeSet::new().with("hello").with(1usize);
+/// let branch = base.with(2usize).without::<&'static str>();
+/// assert_eq!(base.get::<usize>(), Some(&1));
+/// assert_eq!(base.get::<&'static str>(), Some(&"hello"));
+/// assert_eq!(branch.get::<usize>(), Some(&2));
+/// assert!(!branch.contains::<&'static str>());
+/// ```
+pub struct ImmutableTypeSet<S: Storage = SendSync> {
+    root: Link<S>,
+    len: usize,
+}
+
+impl<S: Storage> Clone for ImmutableTypeSet<S> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<S: Storage> Default for ImmutableTypeSet<S> {
+    fn default() -> Self {
+        Self { root: None, len: 0 }
+    }
+}
+
+impl<S: Storage> Debug for ImmutableTypeSet<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut values = Vec::with_capacity(self.len);
+        collect(self.root.as_ref(), &mut values);
+        debug_values(f, "ImmutableTypeSet", values.into_iter())
+    }
+}
+
+impl ImmutableTypeSet {
+    /// Create an empty `ImmutableTypeSet`.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { root: None, len: 0 }
+    }
+}
+
+impl<S: Storage> ImmutableTypeSet<S> {
+    /// Returns true if the `ImmutableTypeSet` contains zero types.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of distinct types in this `ImmutableTypeSet`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if this `ImmutableTypeSet` contains a value for type T
+    #[must_use]
+    pub fn contains<T: Storable<S>>(&self) -> bool {
+        get(self.root.as_ref(), key::<T>()).is_some()
+    }
+
+    /// Immutably borrow a value that has been inserted into this `ImmutableTypeSet`.
+    #[must_use]
+    pub fn get<T: Storable<S>>(&self) -> Option<&T> {
+        get(self.root.as_ref(), key::<T>()).map(|value| unwrap!(value.downcast_ref()))
+    }
+
+    /// Returns a new `ImmutableTypeSet` with `value`, replacing any previous value of this type.
+    ///
+    /// Every other value, and most of the table of types, is shared with `self`.
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with<T: Storable<S>>(&self, value: T) -> Self {
+        self.with_captured(Capture::new(value))
+    }
+
+    /// Returns a new `ImmutableTypeSet` with a [`Capture`], replacing any previous value of this
+    /// type.
+    ///
+    /// Every other value, and most of the table of types, is shared with `self`. Values that were
+    /// captured as [cloneable][Capture::cloneable] can be cloned when converting back into a
+    /// [`TypeSet`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_captured<T: Storable<S>>(&self, capture: Capture<T>) -> Self {
+        let value = Arc::new(Value::captured(capture));
+        self.with_value(key::<T>(), value)
+    }
+
+    fn with_value(&self, key: Key, value: Arc<Value<S>>) -> Self {
+        let (root, replaced) = insert(self.root.as_ref(), Node::new(key, value, None, None));
+        Self {
+            root: Some(root),
+            len: if replaced { self.len } else { self.len + 1 },
+        }
+    }
+
+    /// Returns a new `ImmutableTypeSet` without any value of type T.
+    ///
+    /// Every other value, and most of the table of types, is shared with `self`.
+    #[must_use]
+    pub fn without<T: Storable<S>>(&self) -> Self {
+        match remove(self.root.as_ref(), key::<T>()) {
+            Some(root) => Self {
+                root,
+                len: self.len - 1,
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// Returns true if `self` and `other` share the same value for type T, or if neither contains
+    /// one.
+    #[must_use]
+    pub fn shares<T: Storable<S>>(&self, other: &Self) -> bool {
+        match (
+            get(self.root.as_ref(), key::<T>()),
+            get(other.root.as_ref(), key::<T>()),
+        ) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<S: Storage> From<TypeSet<S>> for ImmutableTypeSet<S> {
+    fn from(type_set: TypeSet<S>) -> Self {
+        type_set
+            .values
+            .into_iter()
+            .fold(Self::default(), |immutable, (key, value)| {
+                immutable.with_value(key, Arc::new(value))
+            })
+    }
+}
+
+/// Whether every value in the tree can be moved or cloned out of it. A value must be cloned if
+/// it, or any node above it, is shared.
+fn convertible<S: Storage>(link: Option<&Arc<Node<S>>>, shared: bool) -> bool {
+    let Some(node) = link else {
+        return true;
+    };
+    let shared = shared || Arc::strong_count(node) > 1;
+    ((!shared && Arc::strong_count(&node.value) == 1) || node.value.vtable.clone.is_some())
+        && convertible(node.left.as_ref(), shared)
+        && convertible(node.right.as_ref(), shared)
+}
+
+/// Move every value out of nodes that are not shared, and clone every value out of nodes that
+/// are
+fn into_values<S: Storage>(link: Link<S>, values: &mut BTreeMap<Key, Value<S>>) {
+    let Some(node) = link else {
+        return;
+    };
+
+    match Arc::try_unwrap(node) {
+        Ok(Node {
+            key,
+            value,
+            left,
+            right,
+            ..
+        }) => {
+            let value = Arc::try_unwrap(value).unwrap_or_else(|value| unwrap!(value.try_clone()));
+            values.insert(key, value);
+            into_values(left, values);
+            into_values(right, values);
+        }
+
+        Err(node) => clone_values(&node, values),
+    }
+}
+
+fn clone_values<S: Storage>(node: &Node<S>, values: &mut BTreeMap<Key, Value<S>>) {
+    values.insert(node.key, unwrap!(node.value.try_clone()));
+    for child in [&node.left, &node.right].into_iter().flatten() {
+        clone_values(child, values);
+    }
+}
+
+/// Converting back into a [`TypeSet`] moves every value that is not shared with another
+/// `ImmutableTypeSet`, and clones every value that is.
+///
+/// The original `ImmutableTypeSet` is returned if any shared value was not captured as
+/// [cloneable][Capture::cloneable].
+impl<S: Storage> TryFrom<ImmutableTypeSet<S>> for TypeSet<S> {
+    type Error = ImmutableTypeSet<S>;
+
+    fn try_from(immutable: ImmutableTypeSet<S>) -> Result<Self, Self::Error> {
+        if !convertible(immutable.root.as_ref(), false) {
+            return Err(immutable);
+        }
+
+        let mut values = BTreeMap::new();
+        into_values(immutable.root, &mut values);
+        Ok(TypeSet::from_values(values))
+    }
+}
@@ -0,0 +1,487 @@
+use crate::{
+    storage::{SendSync, Storage},
+    unwrap, Key, TypeSet, Value,
+};
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::btree_map,
+    fmt::{self, Debug, Formatter},
+    iter::FusedIterator,
+    marker::PhantomData,
+};
+
+fn debug_value<S: Storage>(
+    value: &Value<S>,
+    f: &mut Formatter<'_>,
+    name: &'static str,
+) -> fmt::Result {
+    let mut tuple = f.debug_tuple(name);
+    tuple.field(&value.name);
+    if let Some(debug) = value.vtable.debug {
+        tuple.field(&crate::field_with(|f| debug(S::as_any(&*value.any), f)));
+    }
+    tuple.finish()
+}
+
+/// A type-erased shared view of a value in a [`TypeSet`]
+///
+/// This type is yielded by [`TypeSet::iter`][crate::TypeSet::iter].
+pub struct ErasedRef<'a, S: Storage = SendSync>(&'a Value<S>);
+
+impl<S: Storage> Clone for ErasedRef<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Storage> Copy for ErasedRef<'_, S> {}
+
+impl<S: Storage> Debug for ErasedRef<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        debug_value(self.0, f, "ErasedRef")
+    }
+}
+
+impl<'a, S: Storage> ErasedRef<'a, S> {
+    /// The [`TypeId`] of the stored value
+    #[must_use]
+    pub fn type_id(&self) -> TypeId {
+        S::as_any(&*self.0.any).type_id()
+    }
+
+    /// The [`std::any::type_name`] of the stored value, or of its [key][crate::keyed::Key] if it
+    /// was inserted with one
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.0.name
+    }
+
+    /// Borrow the stored value as [`Any`]
+    #[must_use]
+    pub fn as_any(&self) -> &'a dyn Any {
+        S::as_any(&*self.0.any)
+    }
+
+    /// Borrow the stored value if it is a T
+    #[must_use]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&'a T> {
+        self.as_any().downcast_ref()
+    }
+}
+
+/// A type-erased exclusive view of a value in a [`TypeSet`]
+///
+/// This type is yielded by [`TypeSet::iter_mut`][crate::TypeSet::iter_mut].
+pub struct ErasedMut<'a, S: Storage = SendSync>(&'a mut Value<S>);
+
+impl<S: Storage> Debug for ErasedMut<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        debug_value(self.0, f, "ErasedMut")
+    }
+}
+
+impl<'a, S: Storage> ErasedMut<'a, S> {
+    /// The [`TypeId`] of the stored value
+    #[must_use]
+    pub fn type_id(&self) -> TypeId {
+        S::as_any(&*self.0.any).type_id()
+    }
+
+    /// The [`std::any::type_name`] of the stored value, or of its [key][crate::keyed::Key] if it
+    /// was inserted with one
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.0.name
+    }
+
+    /// Borrow the stored value as [`Any`]
+    #[must_use]
+    pub fn as_any(&self) -> &dyn Any {
+        S::as_any(&*self.0.any)
+    }
+
+    /// Mutably borrow the stored value as [`Any`]
+    #[must_use]
+    pub fn as_any_mut(&mut self) -> &mut dyn Any {
+        S::as_any_mut(&mut *self.0.any)
+    }
+
+    /// Convert this view into a mutable borrow of the stored value as [`Any`]
+    #[must_use]
+    pub fn into_any_mut(self) -> &'a mut dyn Any {
+        S::as_any_mut(&mut *self.0.any)
+    }
+
+    /// Mutably borrow the stored value if it is a T
+    #[must_use]
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+}
+
+/// A type-erased value that has been removed from a [`TypeSet`]
+///
+/// This retains everything that was recorded when the value was inserted, so it can be inserted
+/// into another `TypeSet` with [`TypeSet::insert_erased`][crate::TypeSet::insert_erased], or
+/// collected into one.
+///
+/// This type is yielded by [`TypeSet::into_iter`][crate::TypeSet::into_iter].
+pub struct Erased<S: Storage = SendSync>(Key, Value<S>);
+
+impl<S: Storage> Debug for Erased<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        debug_value(&self.1, f, "Erased")
+    }
+}
+
+impl<S: Storage> Erased<S> {
+    /// The [`TypeId`] of the stored value
+    #[must_use]
+    pub fn type_id(&self) -> TypeId {
+        S::as_any(&*self.1.any).type_id()
+    }
+
+    /// The [`std::any::type_name`] of the stored value, or of its [key][crate::keyed::Key] if it
+    /// was inserted with one
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.1.name
+    }
+
+    /// Borrow the value as [`Any`]
+    #[must_use]
+    pub fn as_any(&self) -> &dyn Any {
+        S::as_any(&*self.1.any)
+    }
+
+    /// Mutably borrow the value as [`Any`]
+    #[must_use]
+    pub fn as_any_mut(&mut self) -> &mut dyn Any {
+        S::as_any_mut(&mut *self.1.any)
+    }
+
+    /// Recover the value as a T
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` if the value is not a T
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        if self.as_any().is::<T>() {
+            Ok(unwrap!(self.1.downcast()))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// An iterator over type-erased views of the values in a [`TypeSet`]
+///
+/// This type is constructed by [`TypeSet::iter`][crate::TypeSet::iter]
+pub struct Iter<'a, S: Storage = SendSync>(btree_map::Values<'a, Key, Value<S>>);
+
+impl<S: Storage> Debug for Iter<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.clone().map(ErasedRef))
+            .finish()
+    }
+}
+
+impl<'a, S: Storage> Iterator for Iter<'a, S> {
+    type Item = ErasedRef<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(ErasedRef)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<S: Storage> DoubleEndedIterator for Iter<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(ErasedRef)
+    }
+}
+
+impl<S: Storage> ExactSizeIterator for Iter<'_, S> {}
+impl<S: Storage> FusedIterator for Iter<'_, S> {}
+
+/// An iterator over type-erased exclusive views of the values in a [`TypeSet`]
+///
+/// This type is constructed by [`TypeSet::iter_mut`][crate::TypeSet::iter_mut]
+pub struct IterMut<'a, S: Storage = SendSync>(btree_map::ValuesMut<'a, Key, Value<S>>);
+
+impl<S: Storage> Debug for IterMut<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IterMut").field(&self.0.len()).finish()
+    }
+}
+
+impl<'a, S: Storage> Iterator for IterMut<'a, S> {
+    type Item = ErasedMut<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(ErasedMut)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<S: Storage> DoubleEndedIterator for IterMut<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(ErasedMut)
+    }
+}
+
+impl<S: Storage> ExactSizeIterator for IterMut<'_, S> {}
+impl<S: Storage> FusedIterator for IterMut<'_, S> {}
+
+/// An owning iterator over the type-erased values in a [`TypeSet`]
+///
+/// This type is constructed by [`TypeSet::into_iter`][crate::TypeSet::into_iter]
+pub struct IntoIter<S: Storage = SendSync>(btree_map::IntoIter<Key, Value<S>>);
+
+impl<S: Storage> Debug for IntoIter<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IntoIter").field(&self.0.len()).finish()
+    }
+}
+
+impl<S: Storage> Iterator for IntoIter<S> {
+    type Item = Erased<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| Erased(key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<S: Storage> DoubleEndedIterator for IntoIter<S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, value)| Erased(key, value))
+    }
+}
+
+impl<S: Storage> ExactSizeIterator for IntoIter<S> {}
+impl<S: Storage> FusedIterator for IntoIter<S> {}
+
+/// An iterator over the values in a [`TypeSet`] that can be viewed as the trait object U
+///
+/// This type is constructed by [`TypeSet::iter_dyn`][crate::TypeSet::iter_dyn]
+pub struct IterDyn<'a, U: ?Sized, S: Storage = SendSync>(
+    btree_map::Values<'a, Key, Value<S>>,
+    PhantomData<fn() -> &'a U>,
+);
+
+impl<U: ?Sized, S: Storage> Debug for IterDyn<'_, U, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "IterDyn<{}>", type_name::<U>())
+    }
+}
+
+impl<'a, U: ?Sized + 'static, S: Storage> Iterator for IterDyn<'a, U, S> {
+    type Item = &'a U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find_map(Value::cast)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
+    }
+}
+
+impl<U: ?Sized + 'static, S: Storage> DoubleEndedIterator for IterDyn<'_, U, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.by_ref().rev().find_map(Value::cast)
+    }
+}
+
+impl<U: ?Sized + 'static, S: Storage> FusedIterator for IterDyn<'_, U, S> {}
+
+impl<S: Storage> TypeSet<S> {
+    /// Iterate over every value that was inserted with a
+    /// [`Capture::cast`][crate::capture::Capture::cast] to the trait object U.
+    ///
+    /// The order of iteration is unspecified.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::fmt::Display;
+    /// use type_set::{capture::Capture, TypeSet};
+    /// let set = TypeSet::new()
+    ///     .with_captured(Capture::new(1usize).cast::<dyn Display>(|n| n))
+    ///     .with_captured(Capture::new("two").cast::<dyn Display>(|s| s))
+    ///     .with(3u8);
+    /// let mut strings = set.iter_dyn::<dyn Display>().map(|d| d.to_string()).collect::<Vec<_>>();
+    /// strings.sort();
+    /// assert_eq!(strings, ["1", "two"]);
+    /// ```
+    #[must_use]
+    pub fn iter_dyn<U: ?Sized + 'static>(&self) -> IterDyn<'_, U, S> {
+        IterDyn(self.values.values(), PhantomData)
+    }
+
+    /// Iterate over type-erased views of every value in this `TypeSet`.
+    ///
+    /// The order of iteration is unspecified.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let set = type_set::TypeSet::new().with("hello").with(1usize);
+    /// let mut names = set.iter().map(|erased| erased.type_name()).collect::<Vec<_>>();
+    /// names.sort();
+    /// assert_eq!(names, ["&str", "usize"]);
+    /// ```
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, S> {
+        Iter(self.values.values())
+    }
+
+    /// Iterate over type-erased exclusive views of every value in this `TypeSet`.
+    ///
+    /// The order of iteration is unspecified.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with(1usize).with(2u8);
+    /// for mut erased in set.iter_mut() {
+    ///     if let Some(n) = erased.downcast_mut::<usize>() {
+    ///         *n += 10;
+    ///     }
+    /// }
+    /// assert_eq!(set.get::<usize>(), Some(&11));
+    /// ```
+    #[must_use]
+    pub fn iter_mut(&mut self) -> IterMut<'_, S> {
+        IterMut(self.values.values_mut())
+    }
+
+    /// Insert a type-erased value that was removed from a `TypeSet`.
+    ///
+    /// If a value with the same key already exists, it will be replaced and returned.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with("hello");
+    /// let mut other = type_set::TypeSet::new();
+    /// for erased in set {
+    ///     other.insert_erased(erased);
+    /// }
+    /// assert_eq!(other.get::<&'static str>(), Some(&"hello"));
+    /// ```
+    pub fn insert_erased(&mut self, erased: Erased<S>) -> Option<Erased<S>> {
+        let Erased(key, value) = erased;
+        let previous = self.values.insert(key, value);
+        self.observers
+            .notify(key, previous.as_ref(), self.values.get(&key));
+        previous.map(|previous| Erased(key, previous))
+    }
+
+    /// Retain only the values for which `f` returns true, dropping the rest.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// mod auth {
+    ///     pub struct Token;
+    /// }
+    /// let mut set = type_set::TypeSet::new().with(auth::Token).with(1usize);
+    /// set.retain(|erased| !erased.type_name().contains("auth::"));
+    /// assert!(!set.contains::<auth::Token>());
+    /// assert!(set.contains::<usize>());
+    /// ```
+    pub fn retain(&mut self, mut f: impl FnMut(ErasedRef<'_, S>) -> bool) {
+        self.values.retain(|_, value| f(ErasedRef(value)));
+    }
+
+    /// Remove every value from this `TypeSet`, returning them as type-erased values.
+    ///
+    /// The `TypeSet` is empty once this returns, even if the iterator is not consumed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with("hello").with(1usize);
+    /// let other = set.drain().collect::<type_set::TypeSet>();
+    /// assert!(set.is_empty());
+    /// assert_eq!(other.len(), 2);
+    /// ```
+    pub fn drain(&mut self) -> IntoIter<S> {
+        IntoIter(std::mem::take(&mut self.values).into_iter())
+    }
+
+    /// Split this `TypeSet` in two, with the values for which `f` returns true in the first
+    /// `TypeSet` and the rest in the second.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let set = type_set::TypeSet::new().with("hello").with(1usize).with(2u8);
+    /// let (numbers, rest) = set.partition(|erased| erased.type_name().starts_with('u'));
+    /// assert_eq!(numbers.len(), 2);
+    /// assert_eq!(rest.get::<&'static str>(), Some(&"hello"));
+    /// ```
+    #[must_use]
+    pub fn partition(self, mut f: impl FnMut(ErasedRef<'_, S>) -> bool) -> (Self, Self) {
+        let (matching, rest) = self
+            .values
+            .into_iter()
+            .partition(|(_, value)| f(ErasedRef(value)));
+        (Self::from_values(matching), Self::from_values(rest))
+    }
+}
+
+impl<S: Storage> IntoIterator for TypeSet<S> {
+    type Item = Erased<S>;
+    type IntoIter = IntoIter<S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.values.into_iter())
+    }
+}
+
+impl<'a, S: Storage> IntoIterator for &'a TypeSet<S> {
+    type Item = ErasedRef<'a, S>;
+    type IntoIter = Iter<'a, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, S: Storage> IntoIterator for &'a mut TypeSet<S> {
+    type Item = ErasedMut<'a, S>;
+    type IntoIter = IterMut<'a, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<S: Storage> Extend<Erased<S>> for TypeSet<S> {
+    fn extend<I: IntoIterator<Item = Erased<S>>>(&mut self, iter: I) {
+        for erased in iter {
+            self.insert_erased(erased);
+        }
+    }
+}
+
+impl<S: Storage> FromIterator<Erased<S>> for TypeSet<S> {
+    fn from_iter<I: IntoIterator<Item = Erased<S>>>(iter: I) -> Self {
+        let mut type_set = Self::default();
+        type_set.extend(iter);
+        type_set
+    }
+}
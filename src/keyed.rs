@@ -0,0 +1,160 @@
+use crate::{
+    audit::{Access, Audit},
+    entry::Entry,
+    key,
+    storage::{Storable, Storage},
+    unwrap, TypeSet,
+};
+use std::{
+    any::{type_name, TypeId},
+    collections::btree_map,
+    marker::PhantomData,
+};
+
+/// A marker type that names a slot in a [`TypeSet`] holding a [`Key::Value`]
+///
+/// Keys allow several slots to share a value type without a newtype wrapper for each. The key
+/// type itself is never stored, so it is usually an uninhabited or unit struct.
+///
+/// A key whose `Value` is itself names the same slot as [`TypeSet::get`], while any other key
+/// names a slot that can only be reached through that key.
+///
+/// Values stored for a key are reported under the key's name, for example in the `Debug` output
+/// of a `TypeSet` and in [`MissingType`][crate::MissingType].
+///
+/// ## Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use type_set::{keyed::Key, TypeSet};
+///
+/// struct ConnectTimeout;
+/// impl Key for ConnectTimeout {
+///     type Value = Duration;
+/// }
+///
+/// struct ReadTimeout;
+/// impl Key for ReadTimeout {
+///     type Value = Duration;
+/// }
+///
+/// let set = TypeSet::new()
+///     .with_key::<ConnectTimeout>(Duration::from_secs(5))
+///     .with_key::<ReadTimeout>(Duration::from_secs(30));
+/// assert_eq!(set.get_key::<ConnectTimeout>(), Some(&Duration::from_secs(5)));
+/// assert_eq!(set.get_key::<ReadTimeout>(), Some(&Duration::from_secs(30)));
+/// assert!(!set.contains::<Duration>());
+/// ```
+pub trait Key: 'static {
+    /// The type of value stored for this key
+    type Value: 'static;
+}
+
+struct Keyed<K>(PhantomData<K>);
+
+pub(crate) fn key_of<K: Key>() -> TypeId {
+    if TypeId::of::<K::Value>() == TypeId::of::<K>() {
+        key::<K>()
+    } else {
+        key::<Keyed<K>>()
+    }
+}
+
+fn record<K: Key>(audit: &Audit, access: Access, hit: bool) {
+    audit.record_key(key_of::<K>(), type_name::<K>(), access, hit);
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Gets the slot for key K for in-place manipulation.
+    ///
+    /// See [`Entry`] for usage.
+    pub fn entry_key<K: Key>(&mut self) -> Entry<'_, K::Value, S>
+    where
+        K::Value: Storable<S>,
+    {
+        let entry = self.values.entry(key_of::<K>());
+        record::<K>(
+            &self.audit,
+            Access::Entry,
+            matches!(entry, btree_map::Entry::Occupied(_)),
+        );
+        Entry::new::<K>(entry, &mut self.observers)
+    }
+
+    /// [`TypeSet::entry_key`] without recording an access
+    pub(crate) fn unaudited_entry_key<K: Key>(&mut self) -> Entry<'_, K::Value, S>
+    where
+        K::Value: Storable<S>,
+    {
+        Entry::new::<K>(self.values.entry(key_of::<K>()), &mut self.observers)
+    }
+
+    /// Insert a value for key K.
+    ///
+    /// If there is already a value for this key, it will be replaced and returned.
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_key<K: Key>(&mut self, value: K::Value) -> Option<K::Value>
+    where
+        K::Value: Storable<S>,
+    {
+        self.unaudited_entry_key::<K>().insert(value)
+    }
+
+    /// Chainable constructor to add a value for key K
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_key<K: Key>(mut self, value: K::Value) -> Self
+    where
+        K::Value: Storable<S>,
+    {
+        self.insert_key::<K>(value);
+        self
+    }
+
+    /// Check if this `TypeSet` contains a value for key K
+    #[must_use]
+    pub fn contains_key<K: Key>(&self) -> bool
+    where
+        K::Value: Storable<S>,
+    {
+        let contains = self.values.contains_key(&key_of::<K>());
+        record::<K>(&self.audit, Access::Contains, contains);
+        contains
+    }
+
+    /// Immutably borrow the value for key K
+    #[must_use]
+    pub fn get_key<K: Key>(&self) -> Option<&K::Value>
+    where
+        K::Value: Storable<S>,
+    {
+        let value = self
+            .values
+            .get(&key_of::<K>())
+            .map(|value| unwrap!(value.downcast_ref()));
+        record::<K>(&self.audit, Access::Get, value.is_some());
+        value
+    }
+
+    /// Mutably borrow the value for key K
+    pub fn get_key_mut<K: Key>(&mut self) -> Option<&mut K::Value>
+    where
+        K::Value: Storable<S>,
+    {
+        let key = key_of::<K>();
+        record::<K>(&self.audit, Access::GetMut, self.values.contains_key(&key));
+        self.values
+            .get_mut(&key)
+            .map(|value| unwrap!(value.downcast_mut()))
+    }
+
+    /// Remove and return the value for key K
+    pub fn take_key<K: Key>(&mut self) -> Option<K::Value>
+    where
+        K::Value: Storable<S>,
+    {
+        let value = self.unaudited_entry_key::<K>().take();
+        record::<K>(&self.audit, Access::Take, value.is_some());
+        value
+    }
+}
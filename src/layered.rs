@@ -0,0 +1,171 @@
+use crate::{
+    audit::Access,
+    entry::Entry,
+    key,
+    storage::{SendSync, Storable, Storage},
+    unwrap, TypeSet,
+};
+use std::fmt::{self, Debug, Formatter};
+
+/// A [`TypeSet`] that falls back to borrowed ancestor layers for lookups.
+///
+/// Reads ([`get`][LayeredTypeSet::get] and [`contains`][LayeredTypeSet::contains]) search the top
+/// layer first and then each ancestor in turn, while writes only ever touch the owned top layer.
+/// Layers are numbered from zero at the top. If the top layer is
+/// [auditing][TypeSet::enable_auditing], each lookup is recorded there once, as a hit if any layer
+/// contains the type.
+///
+/// This type is constructed by [`TypeSet::with_parent`].
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::TypeSet;
+/// let app = TypeSet::new().with("app").with(10usize);
+/// let mut request = TypeSet::new().with("request").with_parent(&app);
+/// assert_eq!(request.get::<&'static str>(), Some(&"request"));
+/// assert_eq!(request.get::<usize>(), Some(&10));
+/// assert_eq!(request.layer_of::<usize>(), Some(1));
+///
+/// assert!(request.get_mut::<usize>().is_none());
+/// request.insert(20usize);
+/// assert_eq!(request.get::<usize>(), Some(&20));
+/// assert_eq!(app.get::<usize>(), Some(&10));
+/// ```
+pub struct LayeredTypeSet<'a, S: Storage = SendSync> {
+    top: TypeSet<S>,
+    ancestors: Vec<&'a TypeSet<S>>,
+}
+
+impl<S: Storage> Debug for LayeredTypeSet<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredTypeSet")
+            .field("top", &self.top)
+            .field("ancestors", &self.ancestors)
+            .finish()
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Use this `TypeSet` as the top layer of a [`LayeredTypeSet`] that falls back to `parent`
+    #[must_use]
+    pub fn with_parent(self, parent: &TypeSet<S>) -> LayeredTypeSet<'_, S> {
+        LayeredTypeSet {
+            top: self,
+            ancestors: vec![parent],
+        }
+    }
+}
+
+impl<'a, S: Storage> LayeredTypeSet<'a, S> {
+    /// Add another ancestor below every existing layer, to be searched last.
+    #[must_use]
+    pub fn with_parent(mut self, parent: &'a TypeSet<S>) -> Self {
+        self.ancestors.push(parent);
+        self
+    }
+
+    fn layers(&self) -> impl Iterator<Item = &TypeSet<S>> {
+        std::iter::once(&self.top).chain(self.ancestors.iter().copied())
+    }
+
+    /// Returns the number of layers, including the top layer
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.ancestors.len() + 1
+    }
+
+    /// Borrow the owned top layer
+    #[must_use]
+    pub fn top(&self) -> &TypeSet<S> {
+        &self.top
+    }
+
+    /// Mutably borrow the owned top layer
+    #[must_use]
+    pub fn top_mut(&mut self) -> &mut TypeSet<S> {
+        &mut self.top
+    }
+
+    /// Discard the ancestors and return the owned top layer
+    #[must_use]
+    pub fn into_top(self) -> TypeSet<S> {
+        self.top
+    }
+
+    /// Find the nearest layer that contains type T, recording the access on the top layer only
+    fn find<T: Storable<S>>(&self, access: Access) -> Option<(&T, usize)> {
+        let found = self.layers().enumerate().find_map(|(layer, type_set)| {
+            let value = type_set.values.get(&key::<T>())?;
+            Some((unwrap!(value.downcast_ref()), layer))
+        });
+        self.top.audit.record::<T>(access, found.is_some());
+        if found.is_none() {
+            self.layers().for_each(TypeSet::check_miss::<T>);
+        }
+        found
+    }
+
+    /// Check if any layer contains a value for type T
+    #[must_use]
+    pub fn contains<T: Storable<S>>(&self) -> bool {
+        self.find::<T>(Access::Contains).is_some()
+    }
+
+    /// Immutably borrow the value for type T from the nearest layer that contains one
+    #[must_use]
+    pub fn get<T: Storable<S>>(&self) -> Option<&T> {
+        self.get_with_layer().map(|(value, _)| value)
+    }
+
+    /// Immutably borrow the value for type T from the nearest layer that contains one, along with
+    /// that layer's number. The top layer is zero.
+    #[must_use]
+    pub fn get_with_layer<T: Storable<S>>(&self) -> Option<(&T, usize)> {
+        self.find(Access::Get)
+    }
+
+    /// Returns the number of the nearest layer that contains type T. The top layer is zero.
+    #[must_use]
+    pub fn layer_of<T: Storable<S>>(&self) -> Option<usize> {
+        self.find::<T>(Access::Contains).map(|(_, layer)| layer)
+    }
+
+    /// Insert a value into the top layer.
+    ///
+    /// If the top layer already contains a value of this type, it will be replaced and returned.
+    /// Ancestor layers are never modified.
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert<T: Storable<S>>(&mut self, value: T) -> Option<T> {
+        self.top.insert(value)
+    }
+
+    /// Chainable constructor to add a type to the top layer
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with<T: Storable<S>>(mut self, value: T) -> Self {
+        self.insert(value);
+        self
+    }
+
+    /// Mutably borrow a value from the top layer.
+    ///
+    /// Values in ancestor layers are not visible to this method.
+    pub fn get_mut<T: Storable<S>>(&mut self) -> Option<&mut T> {
+        self.top.get_mut()
+    }
+
+    /// Remove a value from the top layer.
+    ///
+    /// Any value of this type in an ancestor layer becomes visible again.
+    pub fn take<T: Storable<S>>(&mut self) -> Option<T> {
+        self.top.take()
+    }
+
+    /// Gets the corresponding type in the top layer for in-place manipulation.
+    ///
+    /// See [`Entry`] for usage.
+    pub fn entry<T: Storable<S>>(&mut self) -> Entry<'_, T, S> {
+        self.top.entry()
+    }
+}
@@ -16,8 +16,19 @@ can only be retrieved by naming the type.
 Because types can only be retrieved by naming them, rust's module system allows module-private
 storage in a shared `TypeSet`.
 
-Currently, this crate imposes `Send + Sync` bounds on the stored types, but future versions may
-offer variants without those bounds and/or with Clone bounds.
+[`TypeSet`] imposes `Send + Sync` bounds on the stored types. [`SendTypeSet`] only requires `Send`,
+and [`LocalTypeSet`] has no thread-safety bounds at all, so it can hold `Rc` handles and other
+thread-local state. All three share the same api, including [`entry`].
+
+Stored types are erased, so traits like [`Clone`] and [`Debug`] can only be used on stored values
+when they are recorded at insertion with a [`Capture`].
+
+With the `serde` feature, a `TypeSet` can be serialized and deserialized through a
+[`Registry`][registry::Registry] of named types.
+
+With the `provenance` feature, each value records the source location where it was inserted. This
+is available from [`TypeSet::inserted_at`] and is only shown in the alternate `Debug` format
+(`{:#?}`). The plain `Debug` format, [`MissingType`], and [`Diff`][diff::Diff] never include it.
 
 Implementation is based on
 - <https://github.com/hyperium/http/blob/master/src/extensions.rs>
@@ -26,40 +37,186 @@ Implementation is based on
 */
 use std::{
     any::{type_name, Any, TypeId},
-    collections::BTreeMap,
-    fmt::{self, Debug, Formatter},
+    collections::{btree_map, BTreeMap},
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
 };
 
+#[cfg(feature = "provenance")]
+use std::panic::Location;
+
 /// Types for interacting with a mutable view into a `TypeSet` for a given type
 pub mod entry;
 use entry::Entry;
 
-struct Value {
-    any: Box<dyn Any + Send + Sync>,
+/// Marker types for the bounds a `TypeSet` places on stored types
+pub mod storage;
+use storage::{sealed::Promote, Local, SendOnly, SendSync, Storable, Storage};
+
+/// Recording capabilities of values as they are inserted
+pub mod capture;
+use capture::{Capture, Vtable};
+
+/// Named slots that share a value type
+pub mod keyed;
+
+mod multi;
+
+mod suggest;
+
+/// Detecting distinct types that share a name
+pub mod ambiguity;
+
+/// Comparing the types in two `TypeSet`s
+pub mod diff;
+
+/// Merging `TypeSet`s with control over colliding types
+pub mod merge;
+
+/// Notifying observers when values of a type change
+pub mod observe;
+use observe::Observers;
+
+/// Undoing a group of changes to a `TypeSet` if any of them fail
+pub mod transaction;
+
+/// Counting lookups of each type in a `TypeSet`
+pub mod audit;
+use audit::{Access, Audit};
+
+#[cfg(feature = "provenance")]
+mod provenance;
+
+/// Type-erased iteration over the values in a `TypeSet`
+pub mod iter;
+
+/// Borrowing several types from a `TypeSet` at once
+pub mod many;
+use many::ManyMut;
+
+mod slots;
+
+/// A `TypeSet` that can be shared and mutated between threads
+pub mod sync;
+pub use sync::SyncTypeSet;
+
+/// A `TypeSet` that can be populated through a shared reference, one write per type
+pub mod once;
+pub use once::OnceTypeSet;
+
+/// A persistent `TypeSet` with cheap snapshots
+pub mod immutable;
+pub use immutable::ImmutableTypeSet;
+
+/// A `TypeSet` with fallback lookups in ancestor layers
+pub mod layered;
+pub use layered::LayeredTypeSet;
+
+/// Building values and their dependencies on demand from registered providers
+pub mod providers;
+
+/// Serialization and deserialization of a `TypeSet` through a registry of named types
+#[cfg(feature = "serde")]
+pub mod registry;
+
+struct Value<S: Storage> {
+    any: Box<S::Erased>,
     name: &'static str,
+    /// The type that `name` was taken from, which is the key type for keyed values. This is a
+    /// function rather than a `TypeId` to keep values small.
+    named: fn() -> TypeId,
+    promote: Promote,
+    vtable: Vtable,
+    #[cfg(feature = "provenance")]
+    location: &'static Location<'static>,
 }
 
-impl Value {
-    fn new<T: Any + Send + Sync + 'static>(value: T) -> Self {
+impl<S: Storage> Value<S> {
+    #[cfg_attr(feature = "provenance", track_caller)]
+    fn new<T: Storable<S>>(value: T) -> Self {
+        Self::captured(Capture::new(value))
+    }
+
+    #[cfg_attr(feature = "provenance", track_caller)]
+    fn captured<T: Storable<S>>(
+        Capture {
+            value,
+            vtable,
+            promote,
+        }: Capture<T>,
+    ) -> Self {
         Self {
-            any: Box::new(value),
+            any: value.erase(),
             name: type_name::<T>(),
+            named: TypeId::of::<T>,
+            promote: T::promotions().or(promote),
+            vtable,
+            #[cfg(feature = "provenance")]
+            location: Location::caller(),
         }
     }
 
-    fn downcast_mut<T: Any + Send + Sync + 'static>(&mut self) -> Option<&mut T> {
-        debug_assert_eq!(type_name::<T>(), self.name);
-        self.any.downcast_mut()
+    /// Name this value after N rather than after its own type
+    fn named<N: 'static>(mut self) -> Self {
+        self.name = type_name::<N>();
+        self.named = TypeId::of::<N>;
+        self
+    }
+
+    fn try_clone(&self) -> Option<Self> {
+        let any = (self.vtable.clone?)(S::as_any(&*self.any));
+        Some(Self {
+            any: unwrap!(S::promote(any, &self.promote)),
+            vtable: self.vtable.clone(),
+            ..*self
+        })
+    }
+
+    fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        S::as_any_mut(&mut *self.any).downcast_mut()
+    }
+
+    fn downcast<T: Any>(self) -> Option<T> {
+        S::into_any(self.any).downcast().map(|t| *t).ok()
+    }
+
+    fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        S::as_any(&*self.any).downcast_ref()
+    }
+
+    fn eq(&self, other: &Self) -> Option<bool> {
+        let eq = self.vtable.eq.or(other.vtable.eq)?;
+        Some(eq(S::as_any(&*self.any), S::as_any(&*other.any)))
+    }
+
+    fn cast<U: ?Sized + 'static>(&self) -> Option<&U> {
+        self.vtable.cast(S::as_any(&*self.any))
     }
 
-    fn downcast<T: Any + Send + Sync + 'static>(self) -> Option<T> {
-        debug_assert_eq!(type_name::<T>(), self.name);
-        self.any.downcast().map(|t| *t).ok()
+    fn can_convert<S2: Storage>(&self) -> bool {
+        S2::can_promote(&self.promote)
     }
 
-    fn downcast_ref<T: Any + Send + Sync + 'static>(&self) -> Option<&T> {
-        debug_assert_eq!(type_name::<T>(), self.name);
-        self.any.downcast_ref()
+    fn convert<S2: Storage>(self) -> Value<S2> {
+        let Self {
+            any,
+            name,
+            named,
+            promote,
+            vtable,
+            #[cfg(feature = "provenance")]
+            location,
+        } = self;
+        Value {
+            any: unwrap!(S2::promote(S::into_any(any), &promote)),
+            name,
+            named,
+            promote,
+            vtable,
+            #[cfg(feature = "provenance")]
+            location,
+        }
     }
 }
 
@@ -79,10 +236,50 @@ use unwrap;
 
 /// A collection for heterogenous types
 ///
-/// Note that there is currently no way to iterate over the collection, as there may be types stored
-/// that cannot be named by the calling code
-#[derive(Default)]
-pub struct TypeSet(BTreeMap<Key, Value>);
+/// Because there may be types stored that cannot be named by the calling code, iteration with
+/// [`TypeSet::iter`] and [`TypeSet::iter_mut`] yields type-erased views of each value.
+///
+/// The [`Storage`] parameter selects the bounds on stored types, and defaults to [`SendSync`]. See
+/// [`SendTypeSet`] and [`LocalTypeSet`] for the other variants.
+pub struct TypeSet<S: Storage = SendSync> {
+    values: BTreeMap<Key, Value<S>>,
+    observers: Observers<S>,
+    audit: Audit,
+}
+
+/// A [`TypeSet`] for types that are `Send` but not necessarily `Sync`
+///
+/// As with [`HashMap::new`][std::collections::HashMap::new], `new` is only available for the
+/// default [`TypeSet`], so this is constructed with [`Default::default`].
+///
+/// ## Example
+/// ```rust
+/// use std::cell::Cell;
+/// let set = type_set::SendTypeSet::default().with(Cell::new(1u8));
+/// set.get::<Cell<u8>>().unwrap().set(2);
+/// assert_eq!(set.get::<Cell<u8>>().unwrap().get(), 2);
+/// ```
+pub type SendTypeSet = TypeSet<SendOnly>;
+
+/// A [`TypeSet`] without any thread-safety bounds on stored types
+///
+/// Like [`SendTypeSet`], this is constructed with [`Default::default`].
+///
+/// ## Example
+/// ```rust
+/// use std::{cell::RefCell, rc::Rc};
+/// let shared = Rc::new(RefCell::new(Vec::<u8>::new()));
+/// let mut set = type_set::LocalTypeSet::default().with(Rc::clone(&shared));
+/// set.get_mut::<Rc<RefCell<Vec<u8>>>>().unwrap().borrow_mut().push(1);
+/// assert_eq!(*shared.borrow(), [1]);
+/// ```
+pub type LocalTypeSet = TypeSet<Local>;
+
+impl<S: Storage> Default for TypeSet<S> {
+    fn default() -> Self {
+        Self::from_values(BTreeMap::new())
+    }
+}
 
 fn field_with(f: impl Fn(&mut Formatter) -> fmt::Result) -> impl Debug {
     struct DebugWith<F>(F);
@@ -99,15 +296,40 @@ fn field_with(f: impl Fn(&mut Formatter) -> fmt::Result) -> impl Debug {
     DebugWith(f)
 }
 
-impl Debug for TypeSet {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("TypeSet")
-            .field(&field_with(|f| {
-                let mut values = self.0.values().map(|v| v.name).collect::<Vec<_>>();
-                values.sort_unstable();
-                f.debug_set().entries(values).finish()
+fn debug_values<'a, S: Storage>(
+    f: &mut Formatter<'_>,
+    name: &str,
+    values: impl Iterator<Item = &'a Value<S>>,
+) -> fmt::Result {
+    let mut values = values.collect::<Vec<_>>();
+    values.sort_unstable_by_key(|v| v.name);
+    let mut tuple = f.debug_tuple(name);
+    tuple.field(&field_with(|f| {
+        f.debug_set()
+            .entries(values.iter().map(|value| {
+                field_with(move |f| {
+                    Debug::fmt(value.name, f)?;
+                    #[cfg(feature = "provenance")]
+                    if f.alternate() {
+                        write!(f, " @ {}", value.location)?;
+                    }
+                    match value.vtable.debug {
+                        Some(debug) => {
+                            f.write_str(" => ")?;
+                            debug(S::as_any(&*value.any), f)
+                        }
+                        None => Ok(()),
+                    }
+                })
             }))
             .finish()
+    }));
+    tuple.finish()
+}
+
+impl<S: Storage> Debug for TypeSet<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        debug_values(f, S::NAME, self.values.values())
     }
 }
 
@@ -119,26 +341,48 @@ impl TypeSet {
     /// Create an empty `TypeSet`.
     #[must_use]
     pub const fn new() -> Self {
-        Self(BTreeMap::new())
+        Self::from_values(BTreeMap::new())
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// A `TypeSet` without observers or auditing
+    pub(crate) const fn from_values(values: BTreeMap<Key, Value<S>>) -> Self {
+        Self {
+            values,
+            observers: Observers::new(),
+            audit: Audit::new(),
+        }
     }
 
     /// Returns true if the `TypeSet` contains zero types.
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.values.is_empty()
     }
 
     /// Returns the number of distinct types in this `TypeSet`.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.values.len()
     }
 
     /// Gets the corresponding type in the set for in-place manipulation.
     ///
     /// See [`Entry`] for usage.
-    pub fn entry<T: Send + Sync + 'static>(&mut self) -> Entry<'_, T> {
-        Entry::new(self.0.entry(key::<T>()))
+    pub fn entry<T: Storable<S>>(&mut self) -> Entry<'_, T, S> {
+        let entry = self.values.entry(key::<T>());
+        self.audit.record::<T>(
+            Access::Entry,
+            matches!(entry, btree_map::Entry::Occupied(_)),
+        );
+        Entry::new::<T>(entry, &mut self.observers)
+    }
+
+    /// [`TypeSet::entry`] without recording an access, for operations that are audited
+    /// separately or not at all
+    fn unaudited_entry<T: Storable<S>>(&mut self) -> Entry<'_, T, S> {
+        Entry::new::<T>(self.values.entry(key::<T>()), &mut self.observers)
     }
 
     /// Insert a value into this `TypeSet`.
@@ -152,8 +396,9 @@ impl TypeSet {
     /// assert_eq!(set.get::<&'static str>(), Some(&"world"));
     /// assert_eq!(previous, Some("hello"));
     /// ```
-    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
-        self.entry().insert(value)
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert<T: Storable<S>>(&mut self, value: T) -> Option<T> {
+        self.unaudited_entry().insert(value)
     }
 
     /// Chainable constructor to add a type to this `TypeSet`
@@ -164,7 +409,8 @@ impl TypeSet {
     /// assert_eq!(set.get::<&'static str>(), Some(&"hello"));
     /// ```
     #[must_use]
-    pub fn with<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with<T: Storable<S>>(mut self, value: T) -> Self {
         self.insert(value);
         self
     }
@@ -179,24 +425,35 @@ impl TypeSet {
     /// assert!(!set.contains::<String>());
     /// ```
     #[must_use]
-    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+    pub fn contains<T: Storable<S>>(&self) -> bool {
         #[cfg(feature = "log")]
         log::trace!(
             "contains {}?: {}",
             type_name::<T>(),
-            self.0.contains_key(&TypeId::of::<T>())
+            self.values.contains_key(&TypeId::of::<T>())
         );
-        self.0.contains_key(&key::<T>())
+        let contains = self.values.contains_key(&key::<T>());
+        self.audit.record::<T>(Access::Contains, contains);
+        if !contains {
+            self.check_miss::<T>();
+        }
+        contains
     }
 
     /// Immutably borrow a value that has been inserted into this `TypeSet`.
     #[must_use]
-    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+    pub fn get<T: Storable<S>>(&self) -> Option<&T> {
         #[cfg(feature = "log")]
         log::trace!("getting {}", type_name::<T>(),);
-        self.0
+        let value = self
+            .values
             .get(&key::<T>())
-            .map(|value| unwrap!(value.downcast_ref()))
+            .map(|value| unwrap!(value.downcast_ref()));
+        self.audit.record::<T>(Access::Get, value.is_some());
+        if value.is_none() {
+            self.check_miss::<T>();
+        }
+        value
     }
 
     /// Attempt to mutably borrow to a value that has been inserted into this `TypeSet`.
@@ -210,12 +467,63 @@ impl TypeSet {
     /// }
     /// assert_eq!(set.get::<String>().unwrap(), "hello world");
     /// ```
-    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
-        self.0
-            .get_mut(&key::<T>())
+    pub fn get_mut<T: Storable<S>>(&mut self) -> Option<&mut T> {
+        let key = key::<T>();
+        let contains = self.values.contains_key(&key);
+        self.audit.record::<T>(Access::GetMut, contains);
+        if !contains {
+            self.check_miss::<T>();
+            return None;
+        }
+        self.values
+            .get_mut(&key)
             .map(|value| unwrap!(value.downcast_mut()))
     }
 
+    /// Mutably borrow several distinct types from this `TypeSet` at once.
+    ///
+    /// Returns `None` if any of the types are absent, or if the same type appears more than once
+    /// in the tuple.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with(String::from("hello")).with(1usize);
+    /// let (string, count) = set.get_many_mut::<(String, usize)>().unwrap();
+    /// string.push_str(" world");
+    /// *count += 1;
+    /// assert_eq!(set.get::<String>().unwrap(), "hello world");
+    /// assert_eq!(set.get::<usize>(), Some(&2));
+    /// assert!(set.get_many_mut::<(String, String)>().is_none());
+    /// assert!(set.get_many_mut::<(String, u8)>().is_none());
+    /// ```
+    pub fn get_many_mut<M: ManyMut<S>>(&mut self) -> Option<M::Mut<'_>> {
+        M::get_many_mut(self)
+    }
+
+    /// Borrow the value with the given [`TypeId`] as the trait object U, without naming its
+    /// concrete type.
+    ///
+    /// Returns `None` if there is no value with that `TypeId`, or if it was not inserted with a
+    /// [`Capture::cast`] to U.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::{any::TypeId, fmt::Display};
+    /// use type_set::{capture::Capture, TypeSet};
+    /// let set = TypeSet::new()
+    ///     .with_captured(Capture::new(String::from("hello")).cast::<dyn Display>(|s| s))
+    ///     .with(10usize);
+    /// let display = set.get_dyn::<dyn Display>(TypeId::of::<String>()).unwrap();
+    /// assert_eq!(display.to_string(), "hello");
+    /// assert!(set.get_dyn::<dyn Display>(TypeId::of::<usize>()).is_none());
+    /// ```
+    #[must_use]
+    pub fn get_dyn<U: ?Sized + 'static>(&self, type_id: TypeId) -> Option<&U> {
+        self.values.get(&type_id)?.cast()
+    }
+
     /// Remove a value from this `TypeSet`.
     ///
     /// If a value of this type exists, it will be returned.
@@ -227,8 +535,76 @@ impl TypeSet {
     /// assert_eq!(set.take::<&'static str>(), Some("hello"));
     /// assert_eq!(set.take::<&'static str>(), None);
     /// ```
-    pub fn take<T: Send + Sync + 'static>(&mut self) -> Option<T> {
-        self.entry().take()
+    pub fn take<T: Storable<S>>(&mut self) -> Option<T> {
+        let value = self.unaudited_entry().take();
+        self.audit.record::<T>(Access::Take, value.is_some());
+        if value.is_none() {
+            self.check_miss::<T>();
+        }
+        value
+    }
+
+    /// Immutably borrow a value that has been inserted into this `TypeSet`, or describe what is
+    /// present instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MissingType`] if there is no value of type T.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let set = type_set::TypeSet::new().with("hello").with(1usize);
+    /// assert_eq!(set.try_get::<usize>(), Ok(&1));
+    /// let error = set.try_get::<String>().unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "no alloc::string::String in type-set, which contains: &str, usize (did you mean &str?)"
+    /// );
+    /// ```
+    pub fn try_get<T: Storable<S>>(&self) -> Result<&T, MissingType> {
+        self.get().ok_or_else(|| self.missing::<T>())
+    }
+
+    /// Mutably borrow a value that has been inserted into this `TypeSet`, or describe what is
+    /// present instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MissingType`] if there is no value of type T.
+    pub fn try_get_mut<T: Storable<S>>(&mut self) -> Result<&mut T, MissingType> {
+        if self.values.contains_key(&key::<T>()) {
+            Ok(unwrap!(self.get_mut()))
+        } else {
+            self.audit.record::<T>(Access::GetMut, false);
+            self.check_miss::<T>();
+            Err(self.missing::<T>())
+        }
+    }
+
+    /// Remove a value from this `TypeSet`, or describe what is present instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MissingType`] if there is no value of type T.
+    pub fn try_take<T: Storable<S>>(&mut self) -> Result<T, MissingType> {
+        self.take().ok_or_else(|| self.missing::<T>())
+    }
+
+    fn missing<T: 'static>(&self) -> MissingType {
+        let mut present = self
+            .values
+            .values()
+            .map(|value| value.name)
+            .collect::<Vec<_>>();
+        present.sort_unstable();
+        MissingType {
+            name: type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            suggestions: self.suggestions::<T>(),
+            shadowed: self.shadows::<T>(),
+            present,
+        }
     }
 
     /// Get a value from this `TypeSet` or populate it with the provided default.
@@ -244,7 +620,8 @@ impl TypeSet {
     /// assert_eq!(set.get_or_insert("hello"), &mut "hello");
     /// assert_eq!(set.get_or_insert("world"), &mut "hello");
     /// ```
-    pub fn get_or_insert<T: Send + Sync + 'static>(&mut self, default: T) -> &mut T {
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn get_or_insert<T: Storable<S>>(&mut self, default: T) -> &mut T {
         self.entry().or_insert(default)
     }
 
@@ -262,10 +639,8 @@ impl TypeSet {
     /// assert_eq!(set.get_or_insert_with(|| String::from("hello")), "hello");
     /// assert_eq!(set.get_or_insert_with::<String>(|| panic!("this is never called")), "hello");
     /// ```
-    pub fn get_or_insert_with<T: Send + Sync + 'static>(
-        &mut self,
-        default: impl FnOnce() -> T,
-    ) -> &mut T {
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn get_or_insert_with<T: Storable<S>>(&mut self, default: impl FnOnce() -> T) -> &mut T {
         self.entry().or_insert_with(default)
     }
 
@@ -280,12 +655,14 @@ impl TypeSet {
     /// let ten: usize = *set.get_or_insert_default();
     /// assert_eq!(ten, 10);
     /// ```
-    pub fn get_or_insert_default<T: Default + Send + Sync + 'static>(&mut self) -> &mut T {
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn get_or_insert_default<T: Default + Storable<S>>(&mut self) -> &mut T {
         self.entry().or_default()
     }
 
     /// Merge another `TypeSet` into this one, replacing any collisions
     ///
+    /// See [`TypeSet::merge_with`] to keep existing values or resolve collisions per type.
     ///
     /// ## Example
     ///
@@ -297,7 +674,526 @@ impl TypeSet {
     /// assert_eq!(set_a.get::<u32>(), Some(&32));
     /// assert_eq!(set_a.get::<&'static str>(), Some(&"world"));
     /// ```
-    pub fn merge(&mut self, other: TypeSet) {
-        self.0.extend(other.0);
+    pub fn merge(&mut self, other: TypeSet<S>) {
+        for (key, value) in other.values {
+            let previous = self.values.insert(key, value);
+            self.observers
+                .notify(key, previous.as_ref(), self.values.get(&key));
+        }
+    }
+
+    /// Insert a value into this `TypeSet` along with the capabilities recorded in the [`Capture`].
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use type_set::{capture::Capture, TypeSet};
+    /// let mut set = TypeSet::new();
+    /// set.insert_captured(Capture::new(String::from("hello")).cloneable().debug());
+    /// assert_eq!(format!("{:?}", set.clone()), r#"TypeSet({"alloc::string::String" => "hello"})"#);
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_captured<T: Storable<S>>(&mut self, capture: Capture<T>) -> Option<T> {
+        #[cfg(feature = "log")]
+        log::trace!("inserting captured {}", type_name::<T>());
+        let key = key::<T>();
+        let previous = self.values.insert(key, Value::captured(capture));
+        self.observers
+            .notify(key, previous.as_ref(), self.values.get(&key));
+        previous.map(|value| unwrap!(value.downcast()))
+    }
+
+    /// Chainable constructor to add a [`Capture`] to this `TypeSet`
+    ///
+    /// See [`TypeSet::insert_captured`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_captured<T: Storable<S>>(mut self, capture: Capture<T>) -> Self {
+        self.insert_captured(capture);
+        self
+    }
+
+    /// Insert a value into this `TypeSet`, recording how to clone it.
+    ///
+    /// A `TypeSet` can only be cloned if every value in it was inserted this way. See
+    /// [`TypeSet::try_clone`]. This is shorthand for [`Capture::cloneable`].
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new();
+    /// set.insert_cloneable(String::from("hello"));
+    /// let mut cloned = set.clone();
+    /// cloned.get_mut::<String>().unwrap().push_str(" world");
+    /// assert_eq!(set.get::<String>().unwrap(), "hello");
+    /// assert_eq!(cloned.get::<String>().unwrap(), "hello world");
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_cloneable<T: Storable<S> + Clone>(&mut self, value: T) -> Option<T> {
+        self.insert_captured(Capture::new(value).cloneable())
+    }
+
+    /// Chainable constructor to add a cloneable type to this `TypeSet`
+    ///
+    /// See [`TypeSet::insert_cloneable`].
+    ///
+    /// ## Example
+    /// ```rust
+    /// let set = type_set::TypeSet::new().with_cloneable("hello");
+    /// assert_eq!(set.clone().get::<&'static str>(), Some(&"hello"));
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_cloneable<T: Storable<S> + Clone>(mut self, value: T) -> Self {
+        self.insert_cloneable(value);
+        self
+    }
+
+    /// Insert a value into this `TypeSet`, recording that it is `Send` so that the `TypeSet` can
+    /// be converted into a [`SendTypeSet`].
+    ///
+    /// A value inserted into a [`LocalTypeSet`] with [`TypeSet::insert`] cannot be moved into a
+    /// set with stricter bounds, since its bounds cannot be observed once it has been stored. This
+    /// is shorthand for [`Capture::send`].
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_send<T: Storable<S> + Send>(&mut self, value: T) -> Option<T> {
+        self.insert_captured(Capture::new(value).send())
+    }
+
+    /// Chainable constructor to add a type that is `Send` to this `TypeSet`
+    ///
+    /// See [`TypeSet::insert_send`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_send<T: Storable<S> + Send>(mut self, value: T) -> Self {
+        self.insert_send(value);
+        self
+    }
+
+    /// Insert a value into this `TypeSet`, recording that it is `Send + Sync` so that the
+    /// `TypeSet` can be converted into a [`SendTypeSet`] or a [`TypeSet`].
+    ///
+    /// A value inserted into a [`LocalTypeSet`] or [`SendTypeSet`] with [`TypeSet::insert`]
+    /// cannot be moved into a set with stricter bounds, since its bounds cannot be observed once
+    /// it has been stored. This is shorthand for [`Capture::send_sync`].
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use std::rc::Rc;
+    /// use type_set::{LocalTypeSet, TypeSet};
+    /// let mut local = LocalTypeSet::default().with(Rc::new(1u8));
+    /// local.insert_send_sync("hello");
+    /// let result: Result<TypeSet, _> = local.try_into();
+    /// let mut local = result.unwrap_err();
+    /// local.take::<Rc<u8>>();
+    /// let set: TypeSet = local.try_into().unwrap();
+    /// assert_eq!(set.get::<&'static str>(), Some(&"hello"));
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_send_sync<T: Storable<S> + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.insert_captured(Capture::new(value).send_sync())
+    }
+
+    /// Chainable constructor to add a type that is `Send + Sync` to this `TypeSet`
+    ///
+    /// See [`TypeSet::insert_send_sync`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_send_sync<T: Storable<S> + Send + Sync>(mut self, value: T) -> Self {
+        self.insert_send_sync(value);
+        self
+    }
+
+    /// Insert a value into this `TypeSet`, recording how to format it for the `TypeSet`'s
+    /// [`Debug`] output. This is shorthand for [`Capture::debug`].
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with(1u8);
+    /// set.insert_debug(10usize);
+    /// assert_eq!(format!("{set:?}"), r#"TypeSet({"u8", "usize" => 10})"#);
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_debug<T: Storable<S> + Debug>(&mut self, value: T) -> Option<T> {
+        self.insert_captured(Capture::new(value).debug())
+    }
+
+    /// Chainable constructor to add a type to this `TypeSet` that will be shown in its [`Debug`]
+    /// output
+    ///
+    /// See [`TypeSet::insert_debug`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_debug<T: Storable<S> + Debug>(mut self, value: T) -> Self {
+        self.insert_debug(value);
+        self
+    }
+
+    /// Insert a value into this `TypeSet`, recording how to compare it for equality.
+    ///
+    /// A `TypeSet` can only be compared with [`PartialEq`] if every value in it was inserted this
+    /// way. See [`TypeSet::try_eq`]. This is shorthand for [`Capture::comparable`].
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new();
+    /// set.insert_comparable(String::from("hello"));
+    /// assert_eq!(set, type_set::TypeSet::new().with_comparable(String::from("hello")));
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert_comparable<T: Storable<S> + PartialEq>(&mut self, value: T) -> Option<T> {
+        self.insert_captured(Capture::new(value).comparable())
+    }
+
+    /// Chainable constructor to add a comparable type to this `TypeSet`
+    ///
+    /// See [`TypeSet::insert_comparable`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_comparable<T: Storable<S> + PartialEq>(mut self, value: T) -> Self {
+        self.insert_comparable(value);
+        self
+    }
+
+    /// Attempt to clone this `TypeSet`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotCloneable`] with the names of every type that was not inserted with
+    /// [`TypeSet::insert_cloneable`], [`TypeSet::with_cloneable`], or [`Capture::cloneable`].
+    ///
+    /// ## Example
+    /// ```rust
+    /// let set = type_set::TypeSet::new().with_cloneable("hello").with(10usize);
+    /// let error = set.try_clone().unwrap_err();
+    /// assert_eq!(error.type_names(), ["usize"]);
+    /// ```
+    pub fn try_clone(&self) -> Result<Self, NotCloneable> {
+        let mut not_cloneable = Vec::new();
+        let mut map = BTreeMap::new();
+        for (key, value) in &self.values {
+            match value.try_clone() {
+                Some(value) => {
+                    map.insert(*key, value);
+                }
+                None => not_cloneable.push(value.name),
+            }
+        }
+
+        if not_cloneable.is_empty() {
+            Ok(Self::from_values(map))
+        } else {
+            not_cloneable.sort_unstable();
+            Err(NotCloneable(not_cloneable))
+        }
+    }
+
+    /// Attempt to compare this `TypeSet` with another for equality.
+    ///
+    /// Two `TypeSet`s are equal if they contain the same types, and each value is equal to the
+    /// other `TypeSet`'s value of the same type. Values can be compared if either of them was
+    /// captured as comparable.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotComparable`] with the names of every type that is in both `TypeSet`s but was
+    /// not inserted with [`TypeSet::insert_comparable`], [`TypeSet::with_comparable`], or
+    /// [`Capture::comparable`] on either side. `TypeSet`s that contain different types are never
+    /// equal, so this only fails if they contain the same types.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let set = type_set::TypeSet::new().with_comparable("hello").with(10usize);
+    /// let other = type_set::TypeSet::new().with_comparable("hello").with(10usize);
+    /// let error = set.try_eq(&other).unwrap_err();
+    /// assert_eq!(error.type_names(), ["usize"]);
+    /// assert_eq!(set.try_eq(&type_set::TypeSet::new()), Ok(false));
+    /// ```
+    pub fn try_eq(&self, other: &Self) -> Result<bool, NotComparable> {
+        if !self.values.keys().eq(other.values.keys()) {
+            return Ok(false);
+        }
+
+        let mut not_comparable = Vec::new();
+        let mut equal = true;
+        for (value, other) in self.values.values().zip(other.values.values()) {
+            match value.eq(other) {
+                Some(eq) => equal &= eq,
+                None => not_comparable.push(value.name),
+            }
+        }
+
+        if not_comparable.is_empty() {
+            Ok(equal)
+        } else {
+            not_comparable.sort_unstable();
+            Err(NotComparable(not_comparable))
+        }
+    }
+
+    /// Attempt to feed this `TypeSet` into the given [`Hasher`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotHashable`] with the names of every type that was not inserted with
+    /// [`Capture::hashable`]. Nothing is written to `state` in that case.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use std::hash::{DefaultHasher, Hasher};
+    /// use type_set::{capture::Capture, TypeSet};
+    /// let hash = |set: TypeSet| {
+    ///     let mut state = DefaultHasher::new();
+    ///     set.try_hash(&mut state).unwrap();
+    ///     state.finish()
+    /// };
+    /// let build = || TypeSet::new().with_captured(Capture::new("hello").hashable());
+    /// assert_eq!(hash(build()), hash(build()));
+    ///
+    /// let mut state = DefaultHasher::new();
+    /// let error = TypeSet::new().with(1u8).try_hash(&mut state).unwrap_err();
+    /// assert_eq!(error.type_names(), ["u8"]);
+    /// ```
+    pub fn try_hash<H: Hasher>(&self, state: &mut H) -> Result<(), NotHashable> {
+        let mut not_hashable = self
+            .values
+            .values()
+            .filter(|value| value.vtable.hash.is_none())
+            .map(|value| value.name)
+            .collect::<Vec<_>>();
+
+        if !not_hashable.is_empty() {
+            not_hashable.sort_unstable();
+            return Err(NotHashable(not_hashable));
+        }
+
+        state.write_usize(self.values.len());
+        for (key, value) in &self.values {
+            key.hash(state);
+            unwrap!(value.vtable.hash)(S::as_any(&*value.any), state);
+        }
+        Ok(())
+    }
+
+    fn convert<S2: Storage>(self) -> Result<TypeSet<S2>, Self> {
+        if self.values.values().all(Value::can_convert::<S2>) {
+            Ok(TypeSet::from_values(
+                self.values
+                    .into_iter()
+                    .map(|(key, value)| (key, value.convert()))
+                    .collect(),
+            ))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Clones every value in the `TypeSet`.
+///
+/// # Panics
+///
+/// This will panic if any value was not inserted with [`TypeSet::insert_cloneable`],
+/// [`TypeSet::with_cloneable`], or [`Capture::cloneable`]. Use [`TypeSet::try_clone`] to handle
+/// this case.
+impl<S: Storage> Clone for TypeSet<S> {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+/// Compares every value in the `TypeSet`. See [`TypeSet::try_eq`].
+///
+/// # Panics
+///
+/// This will panic if both `TypeSet`s contain the same types and any value was not inserted with
+/// [`TypeSet::insert_comparable`], [`TypeSet::with_comparable`], or [`Capture::comparable`] on
+/// either side. Use [`TypeSet::try_eq`] to handle this case.
+impl<S: Storage> PartialEq for TypeSet<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.try_eq(other).unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+/// The error returned by [`TypeSet::try_clone`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotCloneable(Vec<&'static str>);
+
+impl NotCloneable {
+    /// The sorted names of the types that could not be cloned
+    #[must_use]
+    pub fn type_names(&self) -> &[&'static str] {
+        &self.0
     }
 }
+
+impl Display for NotCloneable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not clone type-set because these types were not inserted as cloneable: {}",
+            self.0.join(", ")
+        )
+    }
+}
+
+impl Error for NotCloneable {}
+
+/// The error returned by [`TypeSet::try_get`], [`TypeSet::try_get_mut`], and
+/// [`TypeSet::try_take`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingType {
+    name: &'static str,
+    type_id: TypeId,
+    present: Vec<&'static str>,
+    suggestions: Vec<&'static str>,
+    shadowed: bool,
+}
+
+impl MissingType {
+    /// The name of the type that was requested
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The [`TypeId`] of the type that was requested
+    #[must_use]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The sorted names of the types that were present
+    #[must_use]
+    pub fn present_types(&self) -> &[&'static str] {
+        &self.present
+    }
+
+    /// The sorted names of the present types that are close to the requested type. See
+    /// [`TypeSet::suggestions`].
+    #[must_use]
+    pub fn suggestions(&self) -> &[&'static str] {
+        &self.suggestions
+    }
+}
+
+impl Display for MissingType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.present.is_empty() {
+            write!(f, "no {} in empty type-set", self.name)
+        } else {
+            write!(
+                f,
+                "no {} in type-set, which contains: {}",
+                self.name,
+                self.present.join(", ")
+            )?;
+
+            if self.shadowed {
+                f.write_str(
+                    " (a different type with the same name is present, so there may be more than \
+                     one version of its crate)",
+                )?;
+            } else if !self.suggestions.is_empty() {
+                write!(f, " (did you mean {}?)", self.suggestions.join(" or "))?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+impl Error for MissingType {}
+
+/// The error returned by [`TypeSet::try_eq`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotComparable(Vec<&'static str>);
+
+impl NotComparable {
+    /// The sorted names of the types that could not be compared
+    #[must_use]
+    pub fn type_names(&self) -> &[&'static str] {
+        &self.0
+    }
+}
+
+impl Display for NotComparable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not compare type-sets because these types were not inserted as comparable: {}",
+            self.0.join(", ")
+        )
+    }
+}
+
+impl Error for NotComparable {}
+
+/// The error returned by [`TypeSet::try_hash`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotHashable(Vec<&'static str>);
+
+impl NotHashable {
+    /// The sorted names of the types that could not be hashed
+    #[must_use]
+    pub fn type_names(&self) -> &[&'static str] {
+        &self.0
+    }
+}
+
+impl Display for NotHashable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not hash type-set because these types were not inserted as hashable: {}",
+            self.0.join(", ")
+        )
+    }
+}
+
+impl Error for NotHashable {}
+
+macro_rules! loosen {
+    ($from:ty => $to:ty) => {
+        impl From<$from> for $to {
+            fn from(type_set: $from) -> Self {
+                unwrap!(type_set.convert().ok())
+            }
+        }
+    };
+}
+
+loosen!(TypeSet => SendTypeSet);
+loosen!(TypeSet => LocalTypeSet);
+loosen!(SendTypeSet => LocalTypeSet);
+
+macro_rules! tighten {
+    ($from:ty => $to:ty) => {
+        impl TryFrom<$from> for $to {
+            type Error = $from;
+
+            /// Values can only be moved into a set with stricter bounds if they were originally
+            /// inserted into a set with those bounds, or inserted with [`TypeSet::insert_send`],
+            /// [`TypeSet::insert_send_sync`], [`Capture::send`], or [`Capture::send_sync`], since
+            /// the bounds of a type cannot be observed once it has been stored. The original set
+            /// is returned otherwise, and [`TypeSet::insert`] alone never records these bounds.
+            fn try_from(type_set: $from) -> Result<Self, Self::Error> {
+                type_set.convert()
+            }
+        }
+    };
+}
+
+tighten!(LocalTypeSet => TypeSet);
+tighten!(LocalTypeSet => SendTypeSet);
+tighten!(SendTypeSet => TypeSet);
@@ -0,0 +1,57 @@
+use crate::{
+    key,
+    storage::{Storable, Storage},
+    unwrap, Key, TypeSet,
+};
+
+/// A tuple of distinct types that can be mutably borrowed from a [`TypeSet`] at the same time.
+///
+/// This is implemented for tuples of up to eight types. See
+/// [`TypeSet::get_many_mut`][crate::TypeSet::get_many_mut].
+pub trait ManyMut<S: Storage> {
+    /// The tuple of mutable references returned by
+    /// [`TypeSet::get_many_mut`][crate::TypeSet::get_many_mut]
+    type Mut<'a>;
+
+    #[doc(hidden)]
+    fn get_many_mut(type_set: &mut TypeSet<S>) -> Option<Self::Mut<'_>>;
+}
+
+fn distinct(keys: &[Key]) -> bool {
+    keys.iter()
+        .enumerate()
+        .all(|(i, key)| !keys[i + 1..].contains(key))
+}
+
+macro_rules! many_mut {
+    ($($t:ident $v:ident),+) => {
+        impl<S: Storage, $($t: Storable<S>),+> ManyMut<S> for ($($t,)+) {
+            type Mut<'a> = ($(&'a mut $t,)+);
+
+            fn get_many_mut(type_set: &mut TypeSet<S>) -> Option<Self::Mut<'_>> {
+                let keys = [$(key::<$t>()),+];
+                if !distinct(&keys) {
+                    return None;
+                }
+
+                $(let mut $v = None;)+
+                for (type_id, value) in &mut type_set.values {
+                    $(if *type_id == key::<$t>() {
+                        $v = Some(value);
+                    } else)+ {}
+                }
+
+                Some(($(unwrap!($v?.downcast_mut::<$t>()),)+))
+            }
+        }
+    };
+}
+
+many_mut!(A a);
+many_mut!(A a, B b);
+many_mut!(A a, B b, C c);
+many_mut!(A a, B b, C c, D d);
+many_mut!(A a, B b, C c, D d, E e);
+many_mut!(A a, B b, C c, D d, E e, F f);
+many_mut!(A a, B b, C c, D d, E e, F f, G g);
+many_mut!(A a, B b, C c, D d, E e, F f, G g, H h);
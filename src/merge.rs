@@ -0,0 +1,183 @@
+use crate::{
+    key,
+    storage::{SendSync, Storable, Storage},
+    unwrap, Key, TypeSet, Value,
+};
+use std::{
+    collections::{btree_map, BTreeMap},
+    fmt::{self, Debug, Formatter},
+};
+
+/// Types that can combine two values into one, for use with [`MergePolicy::merge`]
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::{merge::{Merge, MergePolicy}, TypeSet};
+/// struct Headers(Vec<(&'static str, &'static str)>);
+/// impl Merge for Headers {
+///     fn merge(&mut self, incoming: Self) {
+///         self.0.extend(incoming.0);
+///     }
+/// }
+///
+/// let mut set = TypeSet::new().with(Headers(vec![("accept", "*/*")]));
+/// let other = TypeSet::new().with(Headers(vec![("host", "example.com")]));
+/// set.merge_with(other, MergePolicy::take_incoming().merge::<Headers>());
+/// assert_eq!(set.get::<Headers>().unwrap().0.len(), 2);
+/// ```
+pub trait Merge {
+    /// Combine `incoming` into `self`
+    fn merge(&mut self, incoming: Self);
+}
+
+impl<T> Merge for Vec<T> {
+    fn merge(&mut self, incoming: Self) {
+        self.extend(incoming);
+    }
+}
+
+impl Merge for String {
+    fn merge(&mut self, incoming: Self) {
+        self.push_str(&incoming);
+    }
+}
+
+type Resolver<'a, S> = Box<dyn FnMut(&mut Value<S>, Value<S>) + 'a>;
+
+/// How [`TypeSet::merge_with`] resolves a type that is present in both `TypeSet`s
+///
+/// A policy either keeps the existing value or takes the incoming one, and can resolve
+/// individual types with a closure or with their [`Merge`] implementation.
+pub struct MergePolicy<'a, S: Storage = SendSync> {
+    take_incoming: bool,
+    resolvers: BTreeMap<Key, Resolver<'a, S>>,
+}
+
+impl<S: Storage> Debug for MergePolicy<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergePolicy")
+            .field("take_incoming", &self.take_incoming)
+            .field("resolvers", &self.resolvers.len())
+            .finish()
+    }
+}
+
+impl<'a, S: Storage> MergePolicy<'a, S> {
+    /// Keep the value that was already in the `TypeSet`, dropping the incoming one
+    #[must_use]
+    pub fn keep_existing() -> Self {
+        Self {
+            take_incoming: false,
+            resolvers: BTreeMap::new(),
+        }
+    }
+
+    /// Replace the value that was already in the `TypeSet` with the incoming one. This is the
+    /// behavior of [`TypeSet::merge`].
+    #[must_use]
+    pub fn take_incoming() -> Self {
+        Self {
+            take_incoming: true,
+            ..Self::keep_existing()
+        }
+    }
+
+    /// Resolve collisions of type T with a closure that receives the existing value and the
+    /// incoming value
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use type_set::{merge::MergePolicy, TypeSet};
+    /// let mut set = TypeSet::new().with(1usize).with("existing");
+    /// let other = TypeSet::new().with(2usize).with("incoming");
+    /// let policy = MergePolicy::keep_existing().resolve(|existing: &mut usize, incoming| {
+    ///     *existing = (*existing).max(incoming);
+    /// });
+    /// set.merge_with(other, policy);
+    /// assert_eq!(set.get::<usize>(), Some(&2));
+    /// assert_eq!(set.get::<&'static str>(), Some(&"existing"));
+    /// ```
+    #[must_use]
+    pub fn resolve<T: Storable<S>>(mut self, mut resolver: impl FnMut(&mut T, T) + 'a) -> Self {
+        self.resolvers.insert(
+            key::<T>(),
+            Box::new(move |existing, incoming| {
+                resolver(
+                    unwrap!(existing.downcast_mut()),
+                    unwrap!(incoming.downcast()),
+                );
+            }),
+        );
+        self
+    }
+
+    /// Resolve collisions of type T with its [`Merge`] implementation
+    #[must_use]
+    pub fn merge<T: Storable<S> + Merge>(self) -> Self {
+        self.resolve(T::merge)
+    }
+}
+
+/// The collisions encountered by [`TypeSet::merge_with`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport(Vec<&'static str>);
+
+impl MergeReport {
+    /// The sorted names of the types that were present in both `TypeSet`s
+    #[must_use]
+    pub fn collisions(&self) -> &[&'static str] {
+        &self.0
+    }
+
+    /// Returns true if no type was present in both `TypeSet`s
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Merge another `TypeSet` into this one, resolving collisions with a [`MergePolicy`].
+    ///
+    /// Types resolved by a closure or [`Merge`] are modified in place, so observers are only
+    /// notified for values that are inserted or replaced.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use type_set::{merge::MergePolicy, TypeSet};
+    /// let mut set = TypeSet::new().with(8u8).with("hello").with(vec![1]);
+    /// let other = TypeSet::new().with(32u32).with("world").with(vec![2]);
+    /// let report = set.merge_with(other, MergePolicy::keep_existing().merge::<Vec<i32>>());
+    /// assert_eq!(report.collisions(), ["&str", "alloc::vec::Vec<i32>"]);
+    /// assert_eq!(set.get::<&'static str>(), Some(&"hello"));
+    /// assert_eq!(set.get::<u32>(), Some(&32));
+    /// assert_eq!(set.get::<Vec<i32>>(), Some(&vec![1, 2]));
+    /// ```
+    pub fn merge_with(&mut self, other: TypeSet<S>, mut policy: MergePolicy<'_, S>) -> MergeReport {
+        let mut collisions = Vec::new();
+        for (key, incoming) in other.values {
+            match self.values.entry(key) {
+                btree_map::Entry::Vacant(vacant) => {
+                    let value = vacant.insert(incoming);
+                    self.observers.notify(key, None, Some(value));
+                }
+
+                btree_map::Entry::Occupied(mut occupied) => {
+                    collisions.push(incoming.name);
+                    if let Some(resolver) = policy.resolvers.get_mut(&key) {
+                        resolver(occupied.get_mut(), incoming);
+                    } else if policy.take_incoming {
+                        let previous = occupied.insert(incoming);
+                        self.observers
+                            .notify(key, Some(&previous), Some(occupied.get()));
+                    }
+                }
+            }
+        }
+        collisions.sort_unstable();
+        MergeReport(collisions)
+    }
+}
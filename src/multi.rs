@@ -0,0 +1,103 @@
+use crate::{
+    entry::Entry,
+    keyed::Key,
+    storage::{Storable, Storage},
+    TypeSet,
+};
+use std::{marker::PhantomData, vec};
+
+/// The key for the ordered collection of every pushed T, kept apart from any `Vec<T>` inserted
+/// directly
+pub(crate) struct All<T>(PhantomData<T>);
+
+impl<T: 'static> Key for All<T> {
+    type Value = Vec<T>;
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Append a value to the ordered collection of T values in this `TypeSet`.
+    ///
+    /// This collection is separate from the single value of type T, and from any `Vec<T>`
+    /// inserted with [`TypeSet::insert`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// struct Warning(&'static str);
+    /// let mut set = type_set::TypeSet::new();
+    /// set.push(Warning("deprecated header"));
+    /// set.push(Warning("slow upstream"));
+    /// let warnings = set.get_all::<Warning>().iter().map(|w| w.0).collect::<Vec<_>>();
+    /// assert_eq!(warnings, ["deprecated header", "slow upstream"]);
+    /// assert!(!set.contains::<Warning>());
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn push<T: Storable<S>>(&mut self, value: T)
+    where
+        Vec<T>: Storable<S>,
+    {
+        self.unaudited_entry_key::<All<T>>()
+            .or_default()
+            .push(value);
+    }
+
+    /// Chainable constructor to append a value to the ordered collection of T values
+    ///
+    /// See [`TypeSet::push`].
+    #[must_use]
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn with_pushed<T: Storable<S>>(mut self, value: T) -> Self
+    where
+        Vec<T>: Storable<S>,
+    {
+        self.push(value);
+        self
+    }
+
+    /// Borrow every T that has been [pushed][TypeSet::push], in order.
+    ///
+    /// Returns an empty slice if none have been pushed.
+    #[must_use]
+    pub fn get_all<T: Storable<S>>(&self) -> &[T]
+    where
+        Vec<T>: Storable<S>,
+    {
+        self.get_key::<All<T>>().map_or(&[], Vec::as_slice)
+    }
+
+    /// Remove every T that has been [pushed][TypeSet::push], returning them in order.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with_pushed(1u8).with_pushed(2u8);
+    /// assert_eq!(set.drain_all::<u8>().collect::<Vec<_>>(), [1, 2]);
+    /// assert!(set.get_all::<u8>().is_empty());
+    /// ```
+    pub fn drain_all<T: Storable<S>>(&mut self) -> vec::IntoIter<T>
+    where
+        Vec<T>: Storable<S>,
+    {
+        self.take_key::<All<T>>().unwrap_or_default().into_iter()
+    }
+
+    /// Gets the ordered collection of [pushed][TypeSet::push] T values for in-place
+    /// manipulation.
+    ///
+    /// The entry is vacant if nothing has been pushed since the collection was last drained.
+    /// See [`Entry`] for usage.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with_pushed("b");
+    /// set.entry_all::<&'static str>().or_default().insert(0, "a");
+    /// assert_eq!(set.get_all::<&'static str>(), ["a", "b"]);
+    /// ```
+    pub fn entry_all<T: Storable<S>>(&mut self) -> Entry<'_, Vec<T>, S>
+    where
+        Vec<T>: Storable<S>,
+    {
+        self.entry_key::<All<T>>()
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    key,
+    storage::{Storable, Storage},
+    unwrap, Key, TypeSet, Value,
+};
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+type CallFn = fn(&mut dyn Any, Option<&dyn Any>, Option<&dyn Any>);
+
+struct Observer<S: Storage> {
+    active: Arc<AtomicBool>,
+    callback: Box<S::Erased>,
+    call: CallFn,
+}
+
+impl<S: Storage> Observer<S> {
+    fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+}
+
+fn call<T: 'static, F: FnMut(Option<&T>, Option<&T>) + 'static>(
+    callback: &mut dyn Any,
+    old: Option<&dyn Any>,
+    new: Option<&dyn Any>,
+) {
+    let callback = unwrap!(callback.downcast_mut::<F>());
+    callback(
+        old.map(|old| unwrap!(old.downcast_ref())),
+        new.map(|new| unwrap!(new.downcast_ref())),
+    );
+}
+
+pub(crate) struct Observers<S: Storage>(BTreeMap<Key, Vec<Observer<S>>>);
+
+impl<S: Storage> Observers<S> {
+    pub(crate) const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    fn subscribe<T: 'static, F: FnMut(Option<&T>, Option<&T>) + Storable<S>>(
+        &mut self,
+        observer: F,
+    ) -> Subscription {
+        let active = Arc::new(AtomicBool::new(true));
+        let observers = self.0.entry(key::<T>()).or_default();
+        observers.retain(Observer::is_active);
+        observers.push(Observer {
+            active: Arc::clone(&active),
+            callback: observer.erase(),
+            call: call::<T, F>,
+        });
+        Subscription(active)
+    }
+
+    pub(crate) fn notify(&mut self, key: Key, old: Option<&Value<S>>, new: Option<&Value<S>>) {
+        let Some(observers) = self.0.get_mut(&key) else {
+            return;
+        };
+
+        observers.retain(Observer::is_active);
+        for observer in observers.iter_mut().filter(|observer| observer.is_active()) {
+            (observer.call)(
+                S::as_any_mut(&mut *observer.callback),
+                old.map(|value| S::as_any(&*value.any)),
+                new.map(|value| S::as_any(&*value.any)),
+            );
+        }
+    }
+}
+
+/// A handle to an observer registered with [`TypeSet::observe`].
+///
+/// Dropping a `Subscription` does not remove the observer. Call
+/// [`Subscription::unsubscribe`] to stop receiving notifications.
+#[derive(Debug, Clone)]
+pub struct Subscription(Arc<AtomicBool>);
+
+impl Subscription {
+    /// Stop notifying this observer of changes.
+    ///
+    /// This can be called from anywhere, including from inside an observer.
+    pub fn unsubscribe(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+
+    /// Returns true until [`Subscription::unsubscribe`] has been called.
+    #[must_use]
+    pub fn is_subscribed(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Register an observer that is called whenever the value for type T is inserted, replaced, or
+    /// removed.
+    ///
+    /// The observer receives the previous value and the current value, either of which is `None`
+    /// if the type was absent. It is notified by [`TypeSet::insert`], [`TypeSet::take`],
+    /// [`TypeSet::merge`], and every entry and capture method that inserts or removes a value.
+    /// Mutation through [`TypeSet::get_mut`] and bulk operations such as [`TypeSet::retain`] and
+    /// [`TypeSet::drain`] are not observed.
+    ///
+    /// The observer has the same thread-safety bounds as the values in this `TypeSet`. Observers
+    /// are not carried over by [`Clone`], conversions between variants, or
+    /// [`TypeSet::partition`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// let changes = Arc::new(Mutex::new(Vec::new()));
+    /// let mut set = type_set::TypeSet::new();
+    /// let subscription = set.observe::<usize>({
+    ///     let changes = Arc::clone(&changes);
+    ///     move |old, new| changes.lock().unwrap().push((old.copied(), new.copied()))
+    /// });
+    ///
+    /// set.insert(1usize);
+    /// set.insert(2usize);
+    /// set.take::<usize>();
+    /// subscription.unsubscribe();
+    /// set.insert(3usize);
+    ///
+    /// assert_eq!(
+    ///     *changes.lock().unwrap(),
+    ///     [(None, Some(1)), (Some(1), Some(2)), (Some(2), None)]
+    /// );
+    /// ```
+    pub fn observe<T: Storable<S>>(
+        &mut self,
+        observer: impl FnMut(Option<&T>, Option<&T>) + Storable<S>,
+    ) -> Subscription {
+        self.observers.subscribe(observer)
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    debug_values, key,
+    slots::Slots,
+    storage::{SendSync, Storable, Storage},
+    unwrap, TypeSet, Value,
+};
+use std::fmt::{self, Debug, Formatter};
+
+/// A collection for heterogenous types that can be populated through a shared reference.
+///
+/// Like [`OnceLock`][std::sync::OnceLock], each type can only be written once. Values are never
+/// replaced or removed while the set is borrowed, so [`get`][OnceTypeSet::get] and
+/// [`get_or_init`][OnceTypeSet::get_or_init] return plain references instead of guards. This
+/// makes it suitable for lazily-initialized caches.
+///
+/// The default `OnceTypeSet` can be shared between threads. When several threads race to
+/// initialize the same type, each of them may run its initializer, but exactly one value is
+/// stored and every caller receives that value. As with [`TypeSet`], the
+/// [`Storage`] parameter relaxes the bounds on stored types, and a `OnceTypeSet<Local>` cannot be
+/// shared between threads.
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::OnceTypeSet;
+/// let set = &OnceTypeSet::new();
+/// let values = std::thread::scope(|scope| {
+///     let handles = (0..4usize)
+///         .map(|n| scope.spawn(move || *set.get_or_init(|| n)))
+///         .collect::<Vec<_>>();
+///     handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+/// });
+/// assert!(values.iter().all(|value| Some(value) == set.get::<usize>()));
+/// assert_eq!(set.set(10usize), Err(10));
+/// ```
+pub struct OnceTypeSet<S: Storage = SendSync>(Slots<Value<S>>);
+
+impl<S: Storage> Default for OnceTypeSet<S> {
+    fn default() -> Self {
+        Self(Slots::default())
+    }
+}
+
+impl<S: Storage> Debug for OnceTypeSet<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        debug_values(f, "OnceTypeSet", self.0.values())
+    }
+}
+
+impl OnceTypeSet {
+    /// Create an empty `OnceTypeSet`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: Storage> OnceTypeSet<S> {
+    /// Returns true if the `OnceTypeSet` contains zero types.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.values().next().is_none()
+    }
+
+    /// Returns the number of distinct types in this `OnceTypeSet`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.values().count()
+    }
+
+    /// Check if this `OnceTypeSet` contains a value for type T
+    #[must_use]
+    pub fn contains<T: Storable<S>>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// Immutably borrow a value that has been inserted into this `OnceTypeSet`.
+    #[must_use]
+    pub fn get<T: Storable<S>>(&self) -> Option<&T> {
+        self.0
+            .get(key::<T>())
+            .map(|value| unwrap!(value.downcast_ref()))
+    }
+
+    /// Mutably borrow a value that has been inserted into this `OnceTypeSet`.
+    pub fn get_mut<T: Storable<S>>(&mut self) -> Option<&mut T> {
+        self.0
+            .get_mut(key::<T>())
+            .map(|value| unwrap!(value.downcast_mut()))
+    }
+
+    /// Insert a value if there is not already a value of this type.
+    ///
+    /// # Errors
+    ///
+    /// Returns `value` if this type was already written.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let set = type_set::OnceTypeSet::new();
+    /// assert_eq!(set.set("hello"), Ok(()));
+    /// assert_eq!(set.set("world"), Err("world"));
+    /// assert_eq!(set.get::<&'static str>(), Some(&"hello"));
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn set<T: Storable<S>>(&self, value: T) -> Result<(), T> {
+        match self.0.insert(key::<T>(), Value::new(value)) {
+            (_, None) => Ok(()),
+            (_, Some(rejected)) => Err(unwrap!(rejected.downcast())),
+        }
+    }
+
+    /// Get the value of type T, or initialize it with `f` if there is none.
+    ///
+    /// Nothing is locked while `f` runs, so it may use this `OnceTypeSet` to initialize other
+    /// types. If this type is written by another caller while `f` is running, including by
+    /// `f` itself, the value returned by `f` is dropped and the value that was written first is
+    /// returned.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let set = type_set::OnceTypeSet::new();
+    /// let greeting = set.get_or_init(|| format!("hello {}", set.get_or_init(|| "world")));
+    /// assert_eq!(greeting, "hello world");
+    /// assert_eq!(set.get_or_init(|| String::from("ignored")), "hello world");
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn get_or_init<T: Storable<S>>(&self, f: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        let (value, _) = self.0.insert(key::<T>(), Value::new(f()));
+        unwrap!(value.downcast_ref())
+    }
+
+    /// Convert this `OnceTypeSet` back into a [`TypeSet`]
+    #[must_use]
+    pub fn into_inner(self) -> TypeSet<S> {
+        TypeSet::from_values(self.0.into_entries().collect())
+    }
+}
+
+impl<S: Storage> From<TypeSet<S>> for OnceTypeSet<S> {
+    fn from(type_set: TypeSet<S>) -> Self {
+        let once = Self::default();
+        for (key, value) in type_set.values {
+            once.0.insert(key, value);
+        }
+        once
+    }
+}
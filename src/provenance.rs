@@ -0,0 +1,31 @@
+use crate::{key, storage::Storage, TypeSet};
+use std::panic::Location;
+
+impl<S: Storage> TypeSet<S> {
+    /// Returns the source location where the current value of type T was inserted.
+    ///
+    /// This is recorded by every method that inserts a value, including [`TypeSet::insert`],
+    /// [`TypeSet::with`], the `get_or_insert` methods, and the insert methods of
+    /// [`Entry`][crate::entry::Entry]. Mutating a value in place does not change its location.
+    /// Values created by deserialization record a location inside this crate.
+    ///
+    /// The location is also shown by the alternate `Debug` format (`{:#?}`), but not by the plain
+    /// `Debug` format, [`MissingType`][crate::MissingType], or [`Diff`][crate::diff::Diff].
+    ///
+    /// Requires the `provenance` feature.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new();
+    /// let line = line!() + 1;
+    /// set.insert("hello");
+    /// let location = set.inserted_at::<&'static str>().unwrap();
+    /// assert_eq!(location.line(), line);
+    /// assert!(set.inserted_at::<String>().is_none());
+    /// ```
+    #[must_use]
+    pub fn inserted_at<T: 'static>(&self) -> Option<&'static Location<'static>> {
+        self.values.get(&key::<T>()).map(|value| value.location)
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    field_with, key,
+    storage::{SendSync, Storable, Storage},
+    unwrap, Key, TypeSet,
+};
+use std::{
+    any::{type_name, Any},
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+/// A tuple of the types that a provider is built from.
+///
+/// This is implemented for `()` and for tuples of up to eight types. See [`Providers::provide`].
+pub trait Dependencies<S: Storage> {
+    /// The tuple of shared references passed to the provider
+    type Refs<'a>;
+
+    #[doc(hidden)]
+    fn types() -> Vec<(Key, &'static str)>;
+
+    #[doc(hidden)]
+    fn get(type_set: &TypeSet<S>) -> Self::Refs<'_>;
+}
+
+impl<S: Storage> Dependencies<S> for () {
+    type Refs<'a> = ();
+
+    fn types() -> Vec<(Key, &'static str)> {
+        Vec::new()
+    }
+
+    fn get(_: &TypeSet<S>) -> Self::Refs<'_> {}
+}
+
+macro_rules! dependencies {
+    ($($t:ident),+) => {
+        impl<S: Storage, $($t: Storable<S>),+> Dependencies<S> for ($($t,)+) {
+            type Refs<'a> = ($(&'a $t,)+);
+
+            fn types() -> Vec<(Key, &'static str)> {
+                vec![$((key::<$t>(), type_name::<$t>())),+]
+            }
+
+            fn get(type_set: &TypeSet<S>) -> Self::Refs<'_> {
+                ($(unwrap!(unwrap!(type_set.values.get(&key::<$t>())).downcast_ref::<$t>()),)+)
+            }
+        }
+    };
+}
+
+dependencies!(A);
+dependencies!(A, B);
+dependencies!(A, B, C);
+dependencies!(A, B, C, D);
+dependencies!(A, B, C, D, E);
+dependencies!(A, B, C, D, E, F);
+dependencies!(A, B, C, D, E, F, G);
+dependencies!(A, B, C, D, E, F, G, H);
+
+type BuildFn<S> = fn(&dyn Any, &mut TypeSet<S>);
+
+struct Provider<S: Storage> {
+    name: &'static str,
+    dependencies: Vec<(Key, &'static str)>,
+    callback: Box<S::Erased>,
+    build: BuildFn<S>,
+}
+
+fn build<S, T, D, F>(provider: &dyn Any, type_set: &mut TypeSet<S>)
+where
+    S: Storage,
+    T: Storable<S>,
+    D: Dependencies<S>,
+    F: Fn(D::Refs<'_>) -> T + 'static,
+{
+    let provider = unwrap!(provider.downcast_ref::<F>());
+    let value = provider(D::get(type_set));
+    type_set.entry::<T>().or_insert(value);
+}
+
+impl<S: Storage> Provider<S> {
+    fn new<T, D, F>(provider: F) -> Self
+    where
+        T: Storable<S>,
+        D: Dependencies<S>,
+        F: Fn(D::Refs<'_>) -> T + Storable<S>,
+    {
+        Self {
+            name: type_name::<T>(),
+            dependencies: D::types(),
+            callback: provider.erase(),
+            build: build::<S, T, D, F>,
+        }
+    }
+}
+
+/// A registry of functions that build values from other values in a [`TypeSet`]
+///
+/// [`Providers::resolve`] builds a type after recursively resolving its dependencies, and inserts
+/// each value that it builds, so every provider runs at most once per `TypeSet`. Values that are
+/// already present are used as they are, whether or not they have a provider.
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::{providers::Providers, TypeSet};
+/// struct Config {
+///     url: &'static str,
+/// }
+/// struct Pool(String);
+/// struct Service(String);
+///
+/// let providers = Providers::new()
+///     .with::<_, (Config,)>(|(config,)| Pool(format!("pool for {}", config.url)))
+///     .with::<_, (Pool, Config)>(|(pool, config)| Service(format!("{} at {}", pool.0, config.url)));
+///
+/// let mut set = TypeSet::new().with(Config { url: "db://" });
+/// let service = providers.resolve::<Service>(&mut set).unwrap();
+/// assert_eq!(service.0, "pool for db:// at db://");
+/// assert!(set.contains::<Pool>());
+/// ```
+pub struct Providers<S: Storage = SendSync>(BTreeMap<Key, Provider<S>>);
+
+impl<S: Storage> Debug for Providers<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut providers = self
+            .0
+            .values()
+            .map(|provider| {
+                let dependencies = provider.dependencies.iter().map(|(_, name)| *name);
+                (provider.name, dependencies.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        providers.sort_unstable();
+        let mut tuple = f.debug_tuple("Providers");
+        tuple.field(&field_with(|f| {
+            f.debug_map()
+                .entries(
+                    providers
+                        .iter()
+                        .map(|(name, dependencies)| (name, dependencies)),
+                )
+                .finish()
+        }));
+        tuple.finish()
+    }
+}
+
+impl<S: Storage> Default for Providers<S> {
+    fn default() -> Self {
+        Self(BTreeMap::new())
+    }
+}
+
+impl Providers {
+    /// Create an empty `Providers` for [`TypeSet`]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+}
+
+impl<S: Storage> Providers<S> {
+    /// Register a function that builds T from the types in the tuple D.
+    ///
+    /// D is usually named explicitly, as in `provide::<T, (A, B)>`, and the provider receives a
+    /// tuple of references to each of those types. The provider has the same thread-safety bounds
+    /// as the values in the `TypeSet`, so the providers for a [`LocalTypeSet`][crate::LocalTypeSet]
+    /// can capture `Rc` handles.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if T already has a provider.
+    pub fn provide<T: Storable<S>, D: Dependencies<S>>(
+        &mut self,
+        provider: impl Fn(D::Refs<'_>) -> T + Storable<S>,
+    ) -> &mut Self {
+        assert!(
+            !self.0.contains_key(&key::<T>()),
+            "type-set provider for {} was registered twice",
+            type_name::<T>()
+        );
+        self.0
+            .insert(key::<T>(), Provider::new::<T, D, _>(provider));
+        self
+    }
+
+    /// Chainable form of [`Providers::provide`]
+    #[must_use]
+    pub fn with<T: Storable<S>, D: Dependencies<S>>(
+        mut self,
+        provider: impl Fn(D::Refs<'_>) -> T + Storable<S>,
+    ) -> Self {
+        self.provide::<T, D>(provider);
+        self
+    }
+
+    /// Returns true if T has a provider
+    #[must_use]
+    pub fn provides<T: 'static>(&self) -> bool {
+        self.0.contains_key(&key::<T>())
+    }
+
+    /// Get the value of type T from `type_set`, building it and any of its missing dependencies
+    /// with their providers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ResolveError::Missing`] if a required type is neither present nor provided, and
+    /// [`ResolveError::Cycle`] if a provider depends on itself, directly or indirectly. Values
+    /// that were built before the error was encountered remain in `type_set`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use type_set::{providers::{Providers, ResolveError}, TypeSet};
+    /// struct A;
+    /// struct B;
+    /// let providers = Providers::new()
+    ///     .with::<_, (B,)>(|_| A)
+    ///     .with::<_, (A,)>(|_| B);
+    /// let error = providers.resolve::<A>(&mut TypeSet::new()).err().unwrap();
+    /// assert!(matches!(error, ResolveError::Cycle(_)));
+    /// assert_eq!(error.type_names().len(), 3);
+    /// assert!(error.to_string().contains("dependency cycle"));
+    /// ```
+    pub fn resolve<'a, T: Storable<S>>(
+        &self,
+        type_set: &'a mut TypeSet<S>,
+    ) -> Result<&'a mut T, ResolveError> {
+        self.resolve_key(key::<T>(), type_name::<T>(), type_set, &mut Vec::new())?;
+        Ok(unwrap!(type_set.get_mut()))
+    }
+
+    fn resolve_key(
+        &self,
+        key: Key,
+        name: &'static str,
+        type_set: &mut TypeSet<S>,
+        path: &mut Vec<(Key, &'static str)>,
+    ) -> Result<(), ResolveError> {
+        if type_set.values.contains_key(&key) {
+            return Ok(());
+        }
+
+        if let Some(start) = path.iter().position(|(k, _)| *k == key) {
+            let mut cycle = path[start..]
+                .iter()
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>();
+            cycle.push(name);
+            return Err(ResolveError::Cycle(cycle));
+        }
+
+        let Some(provider) = self.0.get(&key) else {
+            let mut chain = path.iter().map(|(_, name)| *name).collect::<Vec<_>>();
+            chain.push(name);
+            return Err(ResolveError::Missing(chain));
+        };
+
+        path.push((key, name));
+        for &(key, name) in &provider.dependencies {
+            self.resolve_key(key, name, type_set, path)?;
+        }
+        path.pop();
+
+        (provider.build)(S::as_any(&*provider.callback), type_set);
+        Ok(())
+    }
+}
+
+/// An error returned by [`Providers::resolve`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// The last type is neither present nor provided. The earlier types are the chain of
+    /// dependencies that required it, starting with the requested type.
+    Missing(Vec<&'static str>),
+
+    /// The types form a dependency cycle, which starts and ends with the same type
+    Cycle(Vec<&'static str>),
+}
+
+impl ResolveError {
+    /// The types involved in this error, in dependency order
+    #[must_use]
+    pub fn type_names(&self) -> &[&'static str] {
+        match self {
+            ResolveError::Missing(names) | ResolveError::Cycle(names) => names,
+        }
+    }
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Missing(names) => {
+                let (missing, required_by) = names.split_last().unwrap_or((&"", &[]));
+                write!(
+                    f,
+                    "could not resolve {missing}, which is not in the type-set and has no provider"
+                )?;
+                if !required_by.is_empty() {
+                    write!(f, " (required by {})", required_by.join(" -> "))?;
+                }
+                Ok(())
+            }
+
+            ResolveError::Cycle(names) => write!(
+                f,
+                "could not resolve type-set providers because of a dependency cycle: {}",
+                names.join(" -> ")
+            ),
+        }
+    }
+}
+
+impl Error for ResolveError {}
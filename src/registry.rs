@@ -0,0 +1,316 @@
+use crate::{
+    key,
+    storage::{SendSync, Storable, Storage},
+    unwrap, Key, TypeSet, Value,
+};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed, Error as _, IgnoredAny, MapAccess, Visitor},
+    ser::{Error as _, SerializeMap},
+    Deserializer, Serialize, Serializer,
+};
+use std::{
+    any::{type_name, Any},
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+};
+
+/// What a [`Registry`] does with values that have not been registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unregistered {
+    /// Return an error naming the unregistered type or key. This is the default.
+    #[default]
+    Error,
+
+    /// Leave the value out of the output
+    Skip,
+}
+
+struct Registration<S: Storage> {
+    name: &'static str,
+    type_name: &'static str,
+    serialize: fn(&dyn Any) -> &dyn erased_serde::Serialize,
+    deserialize:
+        fn(&mut dyn erased_serde::Deserializer<'_>) -> Result<Value<S>, erased_serde::Error>,
+}
+
+fn serialize_any<T: Serialize + 'static>(any: &dyn Any) -> &dyn erased_serde::Serialize {
+    unwrap!(any.downcast_ref::<T>())
+}
+
+fn deserialize_value<S: Storage, T: DeserializeOwned + Storable<S>>(
+    deserializer: &mut dyn erased_serde::Deserializer<'_>,
+) -> Result<Value<S>, erased_serde::Error> {
+    erased_serde::deserialize::<T>(deserializer).map(Value::new)
+}
+
+/// A mapping from stable names to the types that can be serialized and deserialized as part of a
+/// [`TypeSet`]
+///
+/// A `TypeSet` serializes as a map from each registered name to its value.
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::{registry::Registry, TypeSet};
+/// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Session {
+///     user_id: u64,
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.register::<Session>("session");
+/// registry.register::<String>("greeting");
+///
+/// let set = TypeSet::new()
+///     .with(Session { user_id: 10 })
+///     .with(String::from("hello"));
+/// let json = serde_json::to_string(&registry.serializable(&set)).unwrap();
+/// assert_eq!(json, r#"{"greeting":"hello","session":{"user_id":10}}"#);
+///
+/// let set = registry
+///     .deserialize(&mut serde_json::Deserializer::from_str(&json))
+///     .unwrap();
+/// assert_eq!(set.get::<Session>(), Some(&Session { user_id: 10 }));
+/// assert_eq!(set.get::<String>().unwrap(), "hello");
+/// ```
+pub struct Registry<S: Storage = SendSync> {
+    by_name: BTreeMap<&'static str, Key>,
+    by_type: BTreeMap<Key, Registration<S>>,
+    unregistered: Unregistered,
+}
+
+impl<S: Storage> Debug for Registry<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field(
+                "types",
+                &crate::field_with(|f| {
+                    f.debug_map()
+                        .entries(self.by_type.values().map(|r| (r.name, r.type_name)))
+                        .finish()
+                }),
+            )
+            .field("unregistered", &self.unregistered)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Storage> Default for Registry<S> {
+    fn default() -> Self {
+        Self {
+            by_name: BTreeMap::new(),
+            by_type: BTreeMap::new(),
+            unregistered: Unregistered::default(),
+        }
+    }
+}
+
+impl Registry {
+    /// Create an empty `Registry` for [`TypeSet`]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            by_name: BTreeMap::new(),
+            by_type: BTreeMap::new(),
+            unregistered: Unregistered::Error,
+        }
+    }
+}
+
+impl<S: Storage> Registry<S> {
+    /// Register type T under the stable `name`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `name` or T has already been registered.
+    pub fn register<T: Serialize + DeserializeOwned + Storable<S>>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self {
+        assert!(
+            !self.by_name.contains_key(name),
+            "type-set registry name {name} was registered twice"
+        );
+        assert!(
+            !self.by_type.contains_key(&key::<T>()),
+            "type-set registry type {} was registered twice",
+            type_name::<T>()
+        );
+        self.by_name.insert(name, key::<T>());
+        self.by_type.insert(
+            key::<T>(),
+            Registration {
+                name,
+                type_name: type_name::<T>(),
+                serialize: serialize_any::<T>,
+                deserialize: deserialize_value::<S, T>,
+            },
+        );
+        self
+    }
+
+    /// Chainable form of [`Registry::register`]
+    #[must_use]
+    pub fn with<T: Serialize + DeserializeOwned + Storable<S>>(
+        mut self,
+        name: &'static str,
+    ) -> Self {
+        self.register::<T>(name);
+        self
+    }
+
+    /// Set the policy for types that are not registered.
+    ///
+    /// This applies both to values in a `TypeSet` that is being serialized and to names in the
+    /// input that is being deserialized.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use type_set::{registry::{Registry, Unregistered}, TypeSet};
+    /// let registry = Registry::new().with::<String>("greeting");
+    /// let set = TypeSet::new().with(String::from("hello")).with(10usize);
+    /// assert!(serde_json::to_string(&registry.serializable(&set)).is_err());
+    ///
+    /// let registry = registry.with_unregistered(Unregistered::Skip);
+    /// let json = serde_json::to_string(&registry.serializable(&set)).unwrap();
+    /// assert_eq!(json, r#"{"greeting":"hello"}"#);
+    /// ```
+    #[must_use]
+    pub fn with_unregistered(mut self, unregistered: Unregistered) -> Self {
+        self.unregistered = unregistered;
+        self
+    }
+
+    /// Returns the policy for types that are not registered
+    #[must_use]
+    pub fn unregistered(&self) -> Unregistered {
+        self.unregistered
+    }
+
+    /// Returns the registered name for T, if any
+    #[must_use]
+    pub fn name_of<T: 'static>(&self) -> Option<&'static str> {
+        self.by_type.get(&key::<T>()).map(|r| r.name)
+    }
+
+    /// Borrow a `TypeSet` as something that can be serialized with this registry
+    #[must_use]
+    pub fn serializable<'a>(&'a self, type_set: &'a TypeSet<S>) -> Serializable<'a, S> {
+        Serializable {
+            registry: self,
+            type_set,
+        }
+    }
+
+    /// Deserialize a `TypeSet` with this registry
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is not a map, if any registered value fails to deserialize,
+    /// or if the input contains an unregistered name and the policy is [`Unregistered::Error`]
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        &self,
+        deserializer: D,
+    ) -> Result<TypeSet<S>, D::Error> {
+        DeserializeSeed::deserialize(self, deserializer)
+    }
+}
+
+/// A [`TypeSet`] borrowed along with the [`Registry`] used to serialize it.
+///
+/// This type is constructed by [`Registry::serializable`]
+pub struct Serializable<'a, S: Storage = SendSync> {
+    registry: &'a Registry<S>,
+    type_set: &'a TypeSet<S>,
+}
+
+impl<S: Storage> Debug for Serializable<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Serializable")
+            .field("registry", self.registry)
+            .field("type_set", self.type_set)
+            .finish()
+    }
+}
+
+impl<S: Storage> Serialize for Serializable<'_, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut entries = Vec::with_capacity(self.type_set.len());
+        for (key, value) in &self.type_set.values {
+            match (self.registry.by_type.get(key), self.registry.unregistered) {
+                (Some(registration), _) => entries.push((registration, value)),
+                (None, Unregistered::Skip) => {}
+                (None, Unregistered::Error) => {
+                    return Err(Ser::Error::custom(format!(
+                        "{} is not registered for serialization",
+                        value.name
+                    )));
+                }
+            }
+        }
+        entries.sort_unstable_by_key(|(registration, _)| registration.name);
+
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (registration, value) in entries {
+            map.serialize_entry(
+                registration.name,
+                (registration.serialize)(S::as_any(&*value.any)),
+            )?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, S: Storage> DeserializeSeed<'de> for &Registry<S> {
+    type Value = TypeSet<S>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, S: Storage> Visitor<'de> for &Registry<S> {
+    type Value = TypeSet<S>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of registered type-set names to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut type_set = TypeSet::default();
+        while let Some(name) = map.next_key::<String>()? {
+            let registration = self
+                .by_name
+                .get(&*name)
+                .map(|key| (key, unwrap!(self.by_type.get(key))));
+
+            match (registration, self.unregistered) {
+                (Some((key, registration)), _) => {
+                    let value = map.next_value_seed(RegistrationSeed(registration))?;
+                    type_set.values.insert(*key, value);
+                }
+                (None, Unregistered::Skip) => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                (None, Unregistered::Error) => {
+                    return Err(A::Error::custom(format!(
+                        "{name} is not registered for deserialization"
+                    )));
+                }
+            }
+        }
+        Ok(type_set)
+    }
+}
+
+struct RegistrationSeed<'a, S: Storage>(&'a Registration<S>);
+
+impl<'de, S: Storage> DeserializeSeed<'de> for RegistrationSeed<'_, S> {
+    type Value = Value<S>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+        (self.0.deserialize)(&mut erased).map_err(D::Error::custom)
+    }
+}
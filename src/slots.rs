@@ -0,0 +1,114 @@
+use crate::{unwrap, Key};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    iter,
+    sync::OnceLock,
+};
+
+const SHARDS: usize = 16;
+
+fn shard_index(key: Key) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    #[allow(clippy::cast_possible_truncation)] // truncation is fine for picking a shard
+    let index = hasher.finish() as usize;
+    index % SHARDS
+}
+
+type Link<V> = OnceLock<Box<Node<V>>>;
+
+struct Node<V> {
+    key: Key,
+    value: V,
+    next: Link<V>,
+}
+
+/// An append-only map that can be extended through a shared reference.
+///
+/// Entries are never moved or removed while the map is borrowed, so references to them last as
+/// long as the borrow of the map. Each key hashes to one of a fixed number of chains, and nothing
+/// is locked except while a single link is written.
+pub(crate) struct Slots<V>([Link<V>; SHARDS]);
+
+impl<V> Default for Slots<V> {
+    fn default() -> Self {
+        Self(std::array::from_fn(|_| OnceLock::new()))
+    }
+}
+
+impl<V> Slots<V> {
+    /// Find the value for `key` in the chain starting at `link`, or the empty link at the end
+    fn find(mut link: &Link<V>, key: Key) -> Result<&V, &Link<V>> {
+        loop {
+            match link.get() {
+                Some(node) if node.key == key => return Ok(&node.value),
+                Some(node) => link = &node.next,
+                None => return Err(link),
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, key: Key) -> Option<&V> {
+        Self::find(&self.0[shard_index(key)], key).ok()
+    }
+
+    pub(crate) fn get_mut(&mut self, key: Key) -> Option<&mut V> {
+        let mut link = &mut self.0[shard_index(key)];
+        while let Some(node) = link.get_mut() {
+            if node.key == key {
+                return Some(&mut node.value);
+            }
+            link = &mut node.next;
+        }
+        None
+    }
+
+    /// Append `value` unless there is already a value for `key`, returning the stored value and,
+    /// if it was not inserted, `value`
+    pub(crate) fn insert(&self, key: Key, value: V) -> (&V, Option<V>) {
+        let mut node = Box::new(Node {
+            key,
+            value,
+            next: OnceLock::new(),
+        });
+        let mut link = &self.0[shard_index(key)];
+        loop {
+            match Self::find(link, key) {
+                Ok(existing) => return (existing, Some(node.value)),
+                Err(empty) => match empty.set(node) {
+                    Ok(()) => return (&unwrap!(empty.get()).value, None),
+                    // another key was appended first, so continue from there
+                    Err(returned) => {
+                        node = returned;
+                        link = empty;
+                    }
+                },
+            }
+        }
+    }
+
+    pub(crate) fn get_or_insert_with(&self, key: Key, f: impl FnOnce() -> V) -> &V {
+        match self.get(key) {
+            Some(value) => value,
+            None => self.insert(key, f()).0,
+        }
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &V> {
+        self.0.iter().flat_map(|link| {
+            iter::successors(link.get(), |node| node.next.get()).map(|node| &node.value)
+        })
+    }
+
+    pub(crate) fn into_entries(self) -> impl Iterator<Item = (Key, V)> {
+        let mut entries = Vec::new();
+        for mut link in self.0 {
+            while let Some(node) = link.take() {
+                let Node { key, value, next } = *node;
+                entries.push((key, value));
+                link = next;
+            }
+        }
+        entries.into_iter()
+    }
+}
@@ -0,0 +1,210 @@
+use std::any::Any;
+
+/// Selects the bounds that a [`TypeSet`][crate::TypeSet] places on the types it stores.
+///
+/// This trait is sealed and is implemented by [`SendSync`], [`SendOnly`], and [`Local`].
+pub trait Storage: sealed::Sealed + 'static {
+    #[doc(hidden)]
+    type Erased: ?Sized + 'static;
+
+    #[doc(hidden)]
+    const NAME: &'static str;
+
+    #[doc(hidden)]
+    fn as_any(erased: &Self::Erased) -> &dyn Any;
+
+    #[doc(hidden)]
+    fn as_any_mut(erased: &mut Self::Erased) -> &mut dyn Any;
+
+    #[doc(hidden)]
+    fn into_any(erased: Box<Self::Erased>) -> Box<dyn Any>;
+
+    #[doc(hidden)]
+    fn can_promote(promote: &Promote) -> bool;
+
+    #[doc(hidden)]
+    fn promote(any: Box<dyn Any>, promote: &Promote) -> Option<Box<Self::Erased>>;
+}
+
+/// A type that can be stored in a [`TypeSet`][crate::TypeSet] with [`Storage`] `S`.
+///
+/// This is implemented for every `'static` type that satisfies the bounds of `S`, and cannot be
+/// implemented outside of this crate.
+pub trait Storable<S: Storage>: Any {
+    #[doc(hidden)]
+    fn erase(self) -> Box<S::Erased>
+    where
+        Self: Sized;
+
+    #[doc(hidden)]
+    fn promotions() -> Promote
+    where
+        Self: Sized;
+}
+
+/// [`Storage`] for types that are `Send + Sync`. This is the default for
+/// [`TypeSet`][crate::TypeSet].
+#[derive(Debug, Clone, Copy)]
+pub struct SendSync;
+
+/// [`Storage`] for types that are `Send` but not necessarily `Sync`. See
+/// [`SendTypeSet`][crate::SendTypeSet].
+#[derive(Debug, Clone, Copy)]
+pub struct SendOnly;
+
+/// [`Storage`] without any thread-safety bounds. See [`LocalTypeSet`][crate::LocalTypeSet].
+#[derive(Debug, Clone, Copy)]
+pub struct Local;
+
+pub(crate) mod sealed {
+    use std::any::Any;
+
+    pub trait Sealed {}
+    impl Sealed for super::SendSync {}
+    impl Sealed for super::SendOnly {}
+    impl Sealed for super::Local {}
+
+    /// The stricter representations a value is known to support, captured when it was inserted.
+    ///
+    /// Bounds cannot be observed after type erasure, so a value inserted into a set with looser
+    /// bounds can only move into a set with stricter ones if they were recorded with
+    /// [`Capture::send`][crate::capture::Capture::send] or
+    /// [`Capture::send_sync`][crate::capture::Capture::send_sync].
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct Promote {
+        pub(crate) send: Option<PromoteFn<dyn Any + Send>>,
+        pub(crate) send_sync: Option<PromoteFn<dyn Any + Send + Sync>>,
+    }
+
+    impl Promote {
+        /// Every representation supported by either `self` or `other`
+        pub(crate) fn or(self, other: Self) -> Self {
+            Self {
+                send: self.send.or(other.send),
+                send_sync: self.send_sync.or(other.send_sync),
+            }
+        }
+    }
+
+    type PromoteFn<Erased> = fn(Box<dyn Any>) -> Box<Erased>;
+
+    pub(crate) fn send<T: Any + Send>(any: Box<dyn Any>) -> Box<dyn Any + Send> {
+        crate::unwrap!(any.downcast::<T>().ok())
+    }
+
+    pub(crate) fn send_sync<T: Any + Send + Sync>(any: Box<dyn Any>) -> Box<dyn Any + Send + Sync> {
+        crate::unwrap!(any.downcast::<T>().ok())
+    }
+}
+use sealed::Promote;
+
+impl Storage for SendSync {
+    type Erased = dyn Any + Send + Sync;
+    const NAME: &'static str = "TypeSet";
+
+    fn as_any(erased: &Self::Erased) -> &dyn Any {
+        erased
+    }
+
+    fn as_any_mut(erased: &mut Self::Erased) -> &mut dyn Any {
+        erased
+    }
+
+    fn into_any(erased: Box<Self::Erased>) -> Box<dyn Any> {
+        erased
+    }
+
+    fn can_promote(promote: &Promote) -> bool {
+        promote.send_sync.is_some()
+    }
+
+    fn promote(any: Box<dyn Any>, promote: &Promote) -> Option<Box<Self::Erased>> {
+        promote.send_sync.map(|f| f(any))
+    }
+}
+
+impl Storage for SendOnly {
+    type Erased = dyn Any + Send;
+    const NAME: &'static str = "SendTypeSet";
+
+    fn as_any(erased: &Self::Erased) -> &dyn Any {
+        erased
+    }
+
+    fn as_any_mut(erased: &mut Self::Erased) -> &mut dyn Any {
+        erased
+    }
+
+    fn into_any(erased: Box<Self::Erased>) -> Box<dyn Any> {
+        erased
+    }
+
+    fn can_promote(promote: &Promote) -> bool {
+        promote.send.is_some()
+    }
+
+    fn promote(any: Box<dyn Any>, promote: &Promote) -> Option<Box<Self::Erased>> {
+        promote.send.map(|f| f(any))
+    }
+}
+
+impl Storage for Local {
+    type Erased = dyn Any;
+    const NAME: &'static str = "LocalTypeSet";
+
+    fn as_any(erased: &Self::Erased) -> &dyn Any {
+        erased
+    }
+
+    fn as_any_mut(erased: &mut Self::Erased) -> &mut dyn Any {
+        erased
+    }
+
+    fn into_any(erased: Box<Self::Erased>) -> Box<dyn Any> {
+        erased
+    }
+
+    fn can_promote(_: &Promote) -> bool {
+        true
+    }
+
+    fn promote(any: Box<dyn Any>, _: &Promote) -> Option<Box<Self::Erased>> {
+        Some(any)
+    }
+}
+
+impl<T: Any + Send + Sync> Storable<SendSync> for T {
+    fn erase(self) -> Box<dyn Any + Send + Sync> {
+        Box::new(self)
+    }
+
+    fn promotions() -> Promote {
+        Promote {
+            send: Some(sealed::send::<T>),
+            send_sync: Some(sealed::send_sync::<T>),
+        }
+    }
+}
+
+impl<T: Any + Send> Storable<SendOnly> for T {
+    fn erase(self) -> Box<dyn Any + Send> {
+        Box::new(self)
+    }
+
+    fn promotions() -> Promote {
+        Promote {
+            send: Some(sealed::send::<T>),
+            send_sync: None,
+        }
+    }
+}
+
+impl<T: Any> Storable<Local> for T {
+    fn erase(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+
+    fn promotions() -> Promote {
+        Promote::default()
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{storage::Storage, TypeSet};
+use std::any::type_name;
+
+/// Types that are commonly wrapped around a value by mistake, compared by their last path
+/// segment
+const WRAPPERS: &[&str] = &[
+    "Arc", "Box", "Cell", "Cow", "Mutex", "Option", "Rc", "RefCell", "RwLock",
+];
+
+/// The type that remains after removing references and single-parameter wrappers, with owned
+/// strings treated as `str`
+fn core(name: &str) -> &str {
+    let name = name
+        .strip_prefix('&')
+        .map_or(name, |name| name.strip_prefix("mut ").unwrap_or(name));
+
+    if let Some((outer, inner)) = name.strip_suffix('>').and_then(|name| name.split_once('<')) {
+        if WRAPPERS.contains(&base(outer)) && !has_top_level_comma(inner) {
+            return core(inner);
+        }
+    }
+
+    match name {
+        "alloc::string::String" => "str",
+        name => name,
+    }
+}
+
+/// The last path segment of a type name, without generic parameters
+fn base(name: &str) -> &str {
+    let name = name.split_once('<').map_or(name, |(outer, _)| outer);
+    name.rsplit_once("::").map_or(name, |(_, base)| base)
+}
+
+/// The generic parameters of a type name, including the angle brackets, or an empty string
+fn generics(name: &str) -> &str {
+    name.find('<').map_or("", |start| &name[start..])
+}
+
+/// Whether two type names differ only in the path leading to the type
+fn same_name(a: &str, b: &str) -> bool {
+    base(a) == base(b) && generics(a) == generics(b)
+}
+
+fn has_top_level_comma(name: &str) -> bool {
+    let mut depth = 0_usize;
+    for c in name.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Returns the sorted names in `stored` that are plausibly what was meant by `requested`: the same
+/// type behind a reference or wrapper, or a type with the same name from a different path.
+pub(crate) fn near_misses(
+    requested: &str,
+    stored: impl Iterator<Item = &'static str>,
+) -> Vec<&'static str> {
+    let requested_core = core(requested);
+    let mut suggestions = stored
+        .filter(|&name| name != requested)
+        .filter(|&name| {
+            let core = core(name);
+            core == requested_core || same_name(core, requested_core)
+        })
+        .collect::<Vec<_>>();
+    suggestions.sort_unstable();
+    suggestions.dedup();
+    suggestions
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Returns the names of stored types that are close to T, for diagnosing a failed lookup.
+    ///
+    /// A stored type is close to T if it is the same type behind a reference or a wrapper such as
+    /// `Arc`, `Box`, or `Option`, if it has the same name from a different path, or if one is a
+    /// `String` and the other is a `str`. This is also included in the message of
+    /// [`MissingType`][crate::MissingType].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// struct Foo;
+    /// let set = type_set::TypeSet::new().with("hello").with(Arc::new(Foo)).with(1usize);
+    /// assert_eq!(set.suggestions::<String>(), ["&str"]);
+    /// assert!(set.suggestions::<Foo>()[0].starts_with("alloc::sync::Arc<"));
+    /// assert!(set.suggestions::<u8>().is_empty());
+    /// ```
+    #[must_use]
+    pub fn suggestions<T: 'static>(&self) -> Vec<&'static str> {
+        near_misses(
+            type_name::<T>(),
+            self.values.values().map(|value| value.name),
+        )
+    }
+}
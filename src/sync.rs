@@ -0,0 +1,293 @@
+use crate::{field_with, key, slots::Slots, storage::SendSync, unwrap, TypeSet, Value};
+use std::{
+    any::type_name,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError},
+};
+
+/// The lock for one type, which is empty if no value is present
+struct Slot {
+    name: &'static str,
+    value: RwLock<Option<Value<SendSync>>>,
+}
+
+impl Slot {
+    fn new(name: &'static str, value: Option<Value<SendSync>>) -> Self {
+        Self {
+            name,
+            value: RwLock::new(value),
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Option<Value<SendSync>>> {
+        self.value.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Option<Value<SendSync>>> {
+        self.value.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether a value is present, without blocking. A slot that is locked for writing is
+    /// reported as present, since it is either borrowed or about to change.
+    fn is_occupied(&self) -> bool {
+        match self.value.try_read() {
+            Ok(value) => value.is_some(),
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().is_some(),
+            Err(TryLockError::WouldBlock) => true,
+        }
+    }
+}
+
+/// A concurrent collection for heterogenous types that can be shared between threads.
+///
+/// Each type has its own lock, so access to one type never waits on access to another. All
+/// operations take `&self`, and borrows are held through [`ReadGuard`] and [`WriteGuard`], which
+/// lock only their type. Accessing a type while the same thread holds a [`WriteGuard`] for it
+/// will deadlock, but any other type can be accessed.
+///
+/// Lock poisoning is ignored, since a panic while a guard is held cannot invalidate the set
+/// itself.
+///
+/// ## Example
+///
+/// ```rust
+/// use type_set::SyncTypeSet;
+/// let set = SyncTypeSet::new();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| set.insert(String::from("hello")));
+///     scope.spawn(|| *set.get_or_insert_with(|| 0usize) += 1);
+/// });
+/// assert_eq!(*set.read::<usize>().unwrap(), 1);
+/// set.write::<String>().unwrap().push_str(" world");
+/// assert_eq!(*set.read::<String>().unwrap(), "hello world");
+/// ```
+pub struct SyncTypeSet(Slots<Slot>);
+
+impl Default for SyncTypeSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for SyncTypeSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SyncTypeSet")
+            .field(&field_with(|f| {
+                let mut values = self
+                    .0
+                    .values()
+                    .filter(|slot| slot.is_occupied())
+                    .map(|slot| slot.name)
+                    .collect::<Vec<_>>();
+                values.sort_unstable();
+                f.debug_set().entries(values).finish()
+            }))
+            .finish()
+    }
+}
+
+impl SyncTypeSet {
+    /// Create an empty `SyncTypeSet`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Slots::default())
+    }
+
+    /// The slot for T, if T has ever been inserted
+    fn existing_slot<T: 'static>(&self) -> Option<&Slot> {
+        self.0.get(key::<T>())
+    }
+
+    /// The slot for T, which is created if T has never been inserted
+    fn slot<T: 'static>(&self) -> &Slot {
+        self.0
+            .get_or_insert_with(key::<T>(), || Slot::new(type_name::<T>(), None))
+    }
+
+    /// Returns true if the `SyncTypeSet` contains zero types.
+    ///
+    /// This does not wait for any lock, so the answer may be stale by the time it is used.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        !self.0.values().any(Slot::is_occupied)
+    }
+
+    /// Returns the number of distinct types in this `SyncTypeSet`.
+    ///
+    /// This does not wait for any lock, so the answer may be stale by the time it is used.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.values().filter(|slot| slot.is_occupied()).count()
+    }
+
+    /// Insert a value into this `SyncTypeSet`.
+    ///
+    /// If a value of this type already exists, it will be replaced and returned.
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        #[cfg(feature = "log")]
+        log::trace!("inserting {}", type_name::<T>());
+        let value = Value::new(value);
+        self.slot::<T>()
+            .write()
+            .replace(value)
+            .map(|value| unwrap!(value.downcast()))
+    }
+
+    /// Check if this `SyncTypeSet` contains a value for type T
+    ///
+    /// This waits if another thread holds a [`WriteGuard`] for T.
+    #[must_use]
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.existing_slot::<T>()
+            .is_some_and(|slot| slot.read().is_some())
+    }
+
+    /// Immutably borrow a value that has been inserted into this `SyncTypeSet`.
+    ///
+    /// Other readers of T can proceed while the [`ReadGuard`] is held, but writers of T will
+    /// block.
+    #[must_use]
+    pub fn read<T: Send + Sync + 'static>(&self) -> Option<ReadGuard<'_, T>> {
+        let guard = self.existing_slot::<T>()?.read();
+        guard.is_some().then(|| ReadGuard(guard, PhantomData))
+    }
+
+    /// Mutably borrow a value that has been inserted into this `SyncTypeSet`.
+    ///
+    /// All other access to T will block while the [`WriteGuard`] is held.
+    #[must_use]
+    pub fn write<T: Send + Sync + 'static>(&self) -> Option<WriteGuard<'_, T>> {
+        let guard = self.existing_slot::<T>()?.write();
+        guard.is_some().then(|| WriteGuard(guard, PhantomData))
+    }
+
+    /// Get a value from this `SyncTypeSet` or populate it with the provided default function.
+    ///
+    /// No lock is held while `default` runs, so it may access other types in this
+    /// `SyncTypeSet`. If several threads race to populate T, each of them may run `default`, but
+    /// only the first value is stored and the others are dropped.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let set = type_set::SyncTypeSet::new();
+    /// set.insert(2u8);
+    /// let value = set.get_or_insert_with(|| u64::from(*set.read::<u8>().unwrap()) * 10);
+    /// assert_eq!(*value, 20);
+    /// ```
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn get_or_insert_with<T: Send + Sync + 'static>(
+        &self,
+        default: impl FnOnce() -> T,
+    ) -> WriteGuard<'_, T> {
+        let slot = self.slot::<T>();
+        let guard = slot.write();
+        if guard.is_some() {
+            return WriteGuard(guard, PhantomData);
+        }
+        drop(guard);
+
+        let value = Value::new(default());
+        let mut guard = slot.write();
+        if guard.is_none() {
+            *guard = Some(value);
+        }
+        WriteGuard(guard, PhantomData)
+    }
+
+    /// Remove a value from this `SyncTypeSet`.
+    ///
+    /// If a value of this type exists, it will be returned.
+    pub fn take<T: Send + Sync + 'static>(&self) -> Option<T> {
+        self.existing_slot::<T>()?
+            .write()
+            .take()
+            .map(|value| unwrap!(value.downcast()))
+    }
+
+    /// Convert this `SyncTypeSet` back into a [`TypeSet`]
+    #[must_use]
+    pub fn into_inner(self) -> TypeSet {
+        TypeSet::from_values(
+            self.0
+                .into_entries()
+                .filter_map(|(key, slot)| {
+                    let value = slot
+                        .value
+                        .into_inner()
+                        .unwrap_or_else(PoisonError::into_inner);
+                    value.map(|value| (key, value))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<TypeSet> for SyncTypeSet {
+    fn from(type_set: TypeSet) -> Self {
+        let sync_type_set = Self::new();
+        for (key, value) in type_set.values {
+            sync_type_set
+                .0
+                .insert(key, Slot::new(value.name, Some(value)));
+        }
+        sync_type_set
+    }
+}
+
+/// A shared borrow of a T stored in a [`SyncTypeSet`]
+///
+/// This type is constructed by [`SyncTypeSet::read`].
+pub struct ReadGuard<'a, T>(
+    RwLockReadGuard<'a, Option<Value<SendSync>>>,
+    PhantomData<fn() -> T>,
+);
+
+/// An exclusive borrow of a T stored in a [`SyncTypeSet`]
+///
+/// This type is constructed by [`SyncTypeSet::write`] and [`SyncTypeSet::get_or_insert_with`].
+pub struct WriteGuard<'a, T>(
+    RwLockWriteGuard<'a, Option<Value<SendSync>>>,
+    PhantomData<fn() -> T>,
+);
+
+impl<T: Send + Sync + 'static> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unwrap!(unwrap!(self.0.as_ref()).downcast_ref())
+    }
+}
+
+impl<T: Send + Sync + 'static> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unwrap!(unwrap!(self.0.as_ref()).downcast_ref())
+    }
+}
+
+impl<T: Send + Sync + 'static> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unwrap!(unwrap!(self.0.as_mut()).downcast_mut())
+    }
+}
+
+impl<T: Debug + Send + Sync + 'static> Debug for ReadGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(&format!("ReadGuard<{}>", type_name::<T>()))
+            .field(&**self)
+            .finish()
+    }
+}
+
+impl<T: Debug + Send + Sync + 'static> Debug for WriteGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(&format!("WriteGuard<{}>", type_name::<T>()))
+            .field(&**self)
+            .finish()
+    }
+}
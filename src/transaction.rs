@@ -0,0 +1,178 @@
+use crate::{
+    key,
+    storage::{SendSync, Storable, Storage},
+    unwrap, Key, TypeSet, Value,
+};
+use std::{
+    collections::{btree_map, BTreeMap},
+    fmt::{self, Debug, Formatter},
+    ops::Deref,
+};
+
+/// A set of changes to a [`TypeSet`] that is undone unless it completes successfully.
+///
+/// This type is constructed by [`TypeSet::transaction`]. It dereferences to the [`TypeSet`] for
+/// reads, and records the original value of each type the first time it is changed so that the
+/// [`TypeSet`] can be restored exactly.
+///
+/// Methods that hand out an original value, either owned or mutably borrowed, require T to be
+/// [`Clone`] so that the original can be kept for rollback. A value that is not `Clone` can be
+/// inserted and [removed][Transaction::remove] inside a transaction, but not changed in place or
+/// [taken][Transaction::take].
+///
+/// Observers are notified of each change as it is made, and of each value restored by a
+/// rollback. A rollback during a panic restores the [`TypeSet`] without notifying observers, so
+/// that a panicking observer cannot abort the process.
+pub struct Transaction<'a, S: Storage = SendSync> {
+    type_set: &'a mut TypeSet<S>,
+    log: BTreeMap<Key, Option<Value<S>>>,
+}
+
+impl<S: Storage> Debug for Transaction<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("type_set", &self.type_set)
+            .field("changed", &self.log.len())
+            .finish()
+    }
+}
+
+impl<S: Storage> Deref for Transaction<'_, S> {
+    type Target = TypeSet<S>;
+
+    fn deref(&self) -> &Self::Target {
+        self.type_set
+    }
+}
+
+impl<S: Storage> TypeSet<S> {
+    /// Run a fallible stage against this `TypeSet`, undoing every change it made if it returns
+    /// `Err` or panics.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error returned by `f`, after this `TypeSet` has been restored.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut set = type_set::TypeSet::new().with(String::from("hello")).with(1usize);
+    /// let result = set.transaction(|tx| {
+    ///     tx.get_mut::<String>().unwrap().push_str(" world");
+    ///     tx.take::<usize>();
+    ///     tx.insert(2u8);
+    ///     Err::<(), _>("stage failed")
+    /// });
+    /// assert_eq!(result, Err("stage failed"));
+    /// assert_eq!(set.get::<String>().unwrap(), "hello");
+    /// assert_eq!(set.get::<usize>(), Some(&1));
+    /// assert!(!set.contains::<u8>());
+    ///
+    /// set.transaction(|tx| {
+    ///     tx.insert(2u8);
+    ///     Ok::<_, ()>(())
+    /// }).unwrap();
+    /// assert_eq!(set.get::<u8>(), Some(&2));
+    /// ```
+    pub fn transaction<R, E>(
+        &mut self,
+        f: impl FnOnce(&mut Transaction<'_, S>) -> Result<R, E>,
+    ) -> Result<R, E> {
+        let mut transaction = Transaction {
+            type_set: self,
+            log: BTreeMap::new(),
+        };
+        let result = f(&mut transaction);
+        if result.is_ok() {
+            transaction.log.clear();
+        }
+        result
+    }
+}
+
+impl<S: Storage> Transaction<'_, S> {
+    /// Insert a value, replacing any previous value of this type.
+    ///
+    /// The previous value is kept in case the transaction is rolled back.
+    #[cfg_attr(feature = "provenance", track_caller)]
+    pub fn insert<T: Storable<S>>(&mut self, value: T) {
+        let key = key::<T>();
+        let previous = self.type_set.values.insert(key, Value::new(value));
+        self.type_set
+            .observers
+            .notify(key, previous.as_ref(), self.type_set.values.get(&key));
+        self.log.entry(key).or_insert(previous);
+    }
+
+    /// Remove the value of type T, returning whether there was one.
+    ///
+    /// The removed value is kept in case the transaction is rolled back.
+    pub fn remove<T: Storable<S>>(&mut self) -> bool {
+        let key = key::<T>();
+        let Some(previous) = self.type_set.values.remove(&key) else {
+            return false;
+        };
+        self.type_set.observers.notify(key, Some(&previous), None);
+        self.log.entry(key).or_insert(Some(previous));
+        true
+    }
+
+    /// Remove and return the value of type T.
+    ///
+    /// If the value was present when the transaction began, a clone is returned and the original
+    /// is kept in case the transaction is rolled back.
+    pub fn take<T: Storable<S> + Clone>(&mut self) -> Option<T> {
+        let key = key::<T>();
+        let previous = self.type_set.values.remove(&key)?;
+        self.type_set.observers.notify(key, Some(&previous), None);
+        match self.log.entry(key) {
+            btree_map::Entry::Vacant(vacant) => {
+                let value = unwrap!(previous.downcast_ref::<T>()).clone();
+                vacant.insert(Some(previous));
+                Some(value)
+            }
+            btree_map::Entry::Occupied(_) => Some(unwrap!(previous.downcast())),
+        }
+    }
+
+    /// Mutably borrow the value of type T.
+    ///
+    /// If the value was present when the transaction began, it is replaced with a clone before it
+    /// is borrowed, and the original is kept in case the transaction is rolled back.
+    pub fn get_mut<T: Storable<S> + Clone>(&mut self) -> Option<&mut T> {
+        let key = key::<T>();
+        if let btree_map::Entry::Vacant(vacant) = self.log.entry(key) {
+            let original = self.type_set.values.get_mut(&key)?;
+            let copy = Value {
+                any: unwrap!(original.downcast_ref::<T>()).clone().erase(),
+                vtable: original.vtable.clone(),
+                ..*original
+            };
+            vacant.insert(Some(std::mem::replace(original, copy)));
+        }
+
+        self.type_set
+            .values
+            .get_mut(&key)
+            .map(|value| unwrap!(value.downcast_mut()))
+    }
+}
+
+impl<S: Storage> Drop for Transaction<'_, S> {
+    fn drop(&mut self) {
+        let notify = !std::thread::panicking();
+        for (key, original) in std::mem::take(&mut self.log) {
+            let current = match original {
+                Some(original) => self.type_set.values.insert(key, original),
+                None => self.type_set.values.remove(&key),
+            };
+            if notify {
+                self.type_set.observers.notify(
+                    key,
+                    current.as_ref(),
+                    self.type_set.values.get(&key),
+                );
+            }
+        }
+    }
+}
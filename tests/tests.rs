@@ -1,9 +1,15 @@
 use std::{
+    cell::{Cell, RefCell},
     panic::{catch_unwind, AssertUnwindSafe},
     process::Termination,
+    rc::Rc,
+    sync::{Arc, Mutex},
 };
 use test_harness::test;
-use type_set::{entry::Entry, TypeSet};
+use type_set::{
+    capture::Capture, entry::Entry, ImmutableTypeSet, LocalTypeSet, SendTypeSet, SyncTypeSet,
+    TypeSet,
+};
 
 fn harness<T: Termination>(f: impl FnOnce() -> T) -> T {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -33,6 +39,31 @@ fn debug() {
         "Vacant(VacantEntry<alloc::string::String>)",
         format!("{:?}", set.entry::<String>())
     );
+
+    set.insert_debug(vec![1u8, 2]);
+    set.insert_captured(Capture::new(String::from("hello")).cloneable().debug());
+    assert_eq!(
+        format!("{set:?}"),
+        r#"TypeSet({"&str", "alloc::string::String" => "hello", "alloc::vec::Vec<u8>" => [1, 2], "tests::MyCustomStruct", "usize"})"#
+    );
+
+    // with the provenance feature, the alternate format also includes insertion locations
+    #[cfg(not(feature = "provenance"))]
+    {
+        let set = TypeSet::new().with_debug(vec![1u8, 2]).with(true);
+        assert_eq!(
+            format!("{set:#?}"),
+            r#"TypeSet(
+    {
+        "alloc::vec::Vec<u8>" => [
+            1,
+            2,
+        ],
+        "bool",
+    },
+)"#
+        );
+    }
 }
 
 #[test(harness)]
@@ -139,3 +170,1320 @@ fn entry() {
 
     assert!(!Entry::from(set.entry::<String>().unwrap_occupied()).is_empty())
 }
+
+#[test(harness)]
+fn variants() {
+    let shared = Rc::new(RefCell::new(String::from("hello")));
+    let mut local = LocalTypeSet::default()
+        .with(Rc::clone(&shared))
+        .with(1usize);
+    local
+        .entry::<Rc<RefCell<String>>>()
+        .unwrap_occupied()
+        .borrow_mut()
+        .push_str(" world");
+    assert_eq!(*shared.borrow(), "hello world");
+    assert_eq!(
+        format!("{local:?}"),
+        r#"LocalTypeSet({"alloc::rc::Rc<core::cell::RefCell<alloc::string::String>>", "usize"})"#
+    );
+
+    let result: Result<TypeSet, _> = local.try_into();
+    let local = result.unwrap_err();
+    assert_eq!(local.len(), 2);
+
+    let mut send = SendTypeSet::default().with(Cell::new(1u8));
+    send.get::<Cell<u8>>().unwrap().set(2);
+    assert_eq!(send.take::<Cell<u8>>().unwrap().get(), 2);
+
+    let set = TypeSet::new().with("hello").with(1usize);
+    let send = SendTypeSet::from(set);
+    let mut local = LocalTypeSet::from(send);
+    local.insert(10usize);
+    let result: Result<TypeSet, _> = local.try_into();
+    let mut local = result.unwrap_err();
+    assert_eq!(local.take::<usize>(), Some(10));
+    let result: Result<TypeSet, _> = local.try_into();
+    assert_eq!(result.unwrap().get::<&'static str>(), Some(&"hello"));
+
+    let send = SendTypeSet::from(TypeSet::new().with("hello")).with(Cell::new(1u8));
+    let local = LocalTypeSet::from(send);
+    let send: SendTypeSet = local.try_into().unwrap();
+    let result: Result<TypeSet, _> = send.try_into();
+    let send = result.unwrap_err();
+    assert_eq!(send.get::<&'static str>(), Some(&"hello"));
+    assert_eq!(send.get::<Cell<u8>>().unwrap().get(), 1);
+
+    let mut local = LocalTypeSet::default()
+        .with_captured(Capture::new("hello").send_sync())
+        .with_captured(Capture::new(Cell::new(1u8)).send());
+    local.insert_captured(Capture::new(10usize).send_sync());
+    let result: Result<TypeSet, _> = local.try_into();
+    let local = result.unwrap_err();
+    let mut send: SendTypeSet = local.try_into().unwrap();
+    assert_eq!(send.take::<Cell<u8>>().unwrap().get(), 1);
+    let set: TypeSet = send.try_into().unwrap();
+    assert_eq!(set.get::<&'static str>(), Some(&"hello"));
+    assert_eq!(set.get::<usize>(), Some(&10));
+
+    let local = LocalTypeSet::default()
+        .with_send(Cell::new(1u8))
+        .with_send_sync(String::from("hello"));
+    let mut send: SendTypeSet = local.try_into().unwrap();
+    send.insert_send_sync(1usize);
+    send.take::<Cell<u8>>();
+    let set: TypeSet = send.try_into().unwrap();
+    assert_eq!(set.len(), 2);
+}
+
+#[test(harness)]
+fn clone() {
+    let mut set = TypeSet::new()
+        .with_cloneable(String::from("hello"))
+        .with_cloneable(vec![1u8, 2, 3]);
+    let mut cloned = set.clone();
+    cloned.get_mut::<String>().unwrap().push_str(" world");
+    assert_eq!(set.get::<String>().unwrap(), "hello");
+    assert_eq!(cloned.get::<String>().unwrap(), "hello world");
+    assert_eq!(cloned.get::<Vec<u8>>().unwrap(), &[1, 2, 3]);
+
+    assert_eq!(
+        set.insert_cloneable(String::from("world")).unwrap(),
+        "hello"
+    );
+
+    let local = LocalTypeSet::from(set.clone())
+        .with_cloneable(Rc::new(()))
+        .with_cloneable(1usize);
+    assert_eq!(local.clone().len(), 4);
+
+    set.insert(MyCustomStruct);
+    set.insert(1usize);
+    let error = set.try_clone().unwrap_err();
+    assert_eq!(error.type_names(), ["tests::MyCustomStruct", "usize"]);
+    assert_eq!(
+        error.to_string(),
+        "could not clone type-set because these types were not inserted as cloneable: \
+         tests::MyCustomStruct, usize"
+    );
+
+    assert_eq!(
+        *catch_unwind(AssertUnwindSafe(|| set.clone()))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap(),
+        error.to_string()
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test(harness)]
+fn serde() {
+    use type_set::registry::{Registry, Unregistered};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Session {
+        user_id: u64,
+    }
+
+    let registry = Registry::new()
+        .with::<Session>("session")
+        .with::<Vec<String>>("tags");
+    assert_eq!(registry.name_of::<Session>(), Some("session"));
+    assert_eq!(registry.name_of::<String>(), None);
+
+    let set = TypeSet::new()
+        .with(Session { user_id: 1 })
+        .with(vec![String::from("a")])
+        .with(MyCustomStruct);
+
+    let error = serde_json::to_string(&registry.serializable(&set)).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "tests::MyCustomStruct is not registered for serialization"
+    );
+
+    let registry = registry.with_unregistered(Unregistered::Skip);
+    let json = serde_json::to_string(&registry.serializable(&set)).unwrap();
+    assert_eq!(json, r#"{"session":{"user_id":1},"tags":["a"]}"#);
+
+    let set = registry
+        .deserialize(&mut serde_json::Deserializer::from_str(
+            r#"{"unknown":[1,2],"tags":["b"],"session":{"user_id":2}}"#,
+        ))
+        .unwrap();
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.get::<Session>(), Some(&Session { user_id: 2 }));
+    assert_eq!(set.get::<Vec<String>>().unwrap(), &["b"]);
+
+    let registry = registry.with_unregistered(Unregistered::Error);
+    let error = registry
+        .deserialize(&mut serde_json::Deserializer::from_str(r#"{"unknown":1}"#))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("unknown is not registered for deserialization"));
+
+    let mut registry = Registry::<type_set::storage::Local>::default();
+    registry.register::<u8>("byte");
+    let local = registry
+        .deserialize(&mut serde_json::Deserializer::from_str(r#"{"byte":8}"#))
+        .unwrap();
+    assert_eq!(local.get::<u8>(), Some(&8));
+}
+
+#[test(harness)]
+fn sync() {
+    let set = SyncTypeSet::from(TypeSet::new().with("hello"));
+    assert!(set.contains::<&'static str>());
+    assert!(!set.is_empty());
+
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                *set.get_or_insert_with(|| 0usize) += 1;
+                set.insert(1u8);
+                *set.write::<u8>().unwrap() += 1;
+            });
+        }
+    });
+
+    assert_eq!(*set.read::<usize>().unwrap(), 8);
+    assert_eq!(set.len(), 3);
+    assert!(set.read::<String>().is_none());
+    assert!(set.write::<String>().is_none());
+    assert_eq!(
+        format!("{:?}", set.read::<usize>().unwrap()),
+        "ReadGuard<usize>(8)"
+    );
+    assert_eq!(
+        format!("{:?}", set.write::<&'static str>().unwrap()),
+        "WriteGuard<&str>(\"hello\")"
+    );
+
+    {
+        let a = set.read::<usize>().unwrap();
+        let b = set.read::<&'static str>().unwrap();
+        assert_eq!((*a, *b), (8, "hello"));
+    }
+
+    assert_eq!(set.insert(10usize), Some(8));
+    assert_eq!(set.take::<u8>(), Some(2));
+    assert_eq!(format!("{set:?}"), r#"SyncTypeSet({"&str", "usize"})"#);
+
+    // each type has its own lock, so holding one never blocks access to another
+    let value = set.get_or_insert_with::<u8>(|| {
+        assert!(!set.contains::<i64>());
+        set.insert(1i64);
+        *set.read::<usize>().unwrap() as u8
+    });
+    assert_eq!(*value, 10);
+    drop(value);
+    {
+        let _usize = set.write::<usize>().unwrap();
+        let _u8 = set.read::<u8>().unwrap();
+        assert_eq!(*set.read::<i64>().unwrap(), 1);
+        assert_eq!(set.len(), 4);
+        assert_eq!(
+            format!("{set:?}"),
+            r#"SyncTypeSet({"&str", "i64", "u8", "usize"})"#
+        );
+    }
+    assert_eq!(set.take::<i64>(), Some(1));
+    assert_eq!(set.take::<u8>(), Some(10));
+    assert_eq!(set.take::<u8>(), None);
+
+    let set = set.into_inner();
+    assert_eq!(set.get::<usize>(), Some(&10));
+    assert_eq!(set.len(), 2);
+}
+
+#[test(harness)]
+fn get_many_mut() {
+    let mut set = TypeSet::new()
+        .with(String::from("hello"))
+        .with(1usize)
+        .with(vec![1u8])
+        .with(true);
+
+    let (string, count, bytes) = set.get_many_mut::<(String, usize, Vec<u8>)>().unwrap();
+    string.push_str(" world");
+    *count += 1;
+    bytes.push(2);
+    assert_eq!(set.get::<String>().unwrap(), "hello world");
+    assert_eq!(set.get::<usize>(), Some(&2));
+    assert_eq!(set.get::<Vec<u8>>().unwrap(), &[1, 2]);
+
+    let (flag,) = set.get_many_mut::<(bool,)>().unwrap();
+    *flag = false;
+    assert_eq!(set.get::<bool>(), Some(&false));
+
+    assert!(set.get_many_mut::<(String, usize, String)>().is_none());
+    assert!(set.get_many_mut::<(String, MyCustomStruct)>().is_none());
+}
+
+#[test(harness)]
+fn iter() {
+    let mut set = TypeSet::new()
+        .with("hello")
+        .with_debug(1usize)
+        .with(MyCustomStruct);
+
+    let mut names = set
+        .iter()
+        .map(|erased| erased.type_name())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(names, ["&str", "tests::MyCustomStruct", "usize"]);
+    assert_eq!(set.iter().len(), 3);
+
+    let erased = set
+        .iter()
+        .find(|erased| erased.type_id() == std::any::TypeId::of::<usize>())
+        .unwrap();
+    assert_eq!(erased.downcast_ref::<usize>(), Some(&1));
+    assert!(erased.as_any().is::<usize>());
+    assert_eq!(format!("{erased:?}"), r#"ErasedRef("usize", 1)"#);
+
+    for mut erased in &mut set {
+        if let Some(n) = erased.downcast_mut::<usize>() {
+            *n += 1;
+        }
+    }
+    assert_eq!(set.get::<usize>(), Some(&2));
+
+    let mut other = TypeSet::new().with(10usize).with(true);
+    let mut replaced = Vec::new();
+    for erased in set {
+        if let Some(previous) = other.insert_erased(erased) {
+            replaced.push(previous);
+        }
+    }
+    assert_eq!(other.len(), 4);
+    assert_eq!(other.get::<usize>(), Some(&2));
+    assert_eq!(format!("{other:?}").matches(r#""usize" => 2"#).count(), 1);
+    let previous = replaced.pop().unwrap();
+    assert_eq!(previous.type_name(), "usize");
+    let previous = previous.downcast::<String>().unwrap_err();
+    assert_eq!(previous.downcast::<usize>().unwrap(), 10);
+
+    let collected = other
+        .into_iter()
+        .filter(|erased| erased.type_name() != "bool")
+        .collect::<TypeSet>();
+    assert_eq!(collected.len(), 3);
+    assert!(!collected.contains::<bool>());
+}
+
+mod auth {
+    pub struct Token;
+    pub struct User(pub u64);
+}
+
+#[test(harness)]
+fn retain_drain_partition() {
+    let mut set = TypeSet::new()
+        .with(auth::Token)
+        .with(auth::User(1))
+        .with(1usize)
+        .with("hello");
+
+    let (auth, mut rest) = set.partition(|erased| erased.type_name().starts_with("tests::auth::"));
+    assert_eq!(auth.len(), 2);
+    assert_eq!(auth.get::<auth::User>().unwrap().0, 1);
+    assert_eq!(rest.len(), 2);
+
+    rest.retain(|erased| erased.type_id() != std::any::TypeId::of::<usize>());
+    assert_eq!(format!("{rest:?}"), r#"TypeSet({"&str"})"#);
+
+    set = auth;
+    let mut other = TypeSet::new().with(1u8);
+    other.extend(set.drain());
+    assert!(set.is_empty());
+    assert_eq!(other.len(), 3);
+    assert!(other.contains::<auth::Token>());
+}
+
+#[test(harness)]
+fn layered() {
+    let global = TypeSet::new().with("global").with(1u8);
+    let app = TypeSet::new().with("app").with(2u16);
+    let mut request = TypeSet::new()
+        .with(3u32)
+        .with_parent(&app)
+        .with_parent(&global)
+        .with(true);
+
+    assert_eq!(request.depth(), 3);
+    assert_eq!(request.get_with_layer::<&'static str>(), Some((&"app", 1)));
+    assert_eq!(request.get_with_layer::<u8>(), Some((&1, 2)));
+    assert_eq!(request.layer_of::<u32>(), Some(0));
+    assert_eq!(request.layer_of::<bool>(), Some(0));
+    assert_eq!(request.layer_of::<String>(), None);
+    assert!(request.contains::<u16>());
+    assert!(!request.contains::<String>());
+
+    assert!(request.get_mut::<u8>().is_none());
+    *request.entry::<u8>().or_default() += 10;
+    assert_eq!(request.get_with_layer::<u8>(), Some((&10, 0)));
+    assert_eq!(global.get::<u8>(), Some(&1));
+
+    assert_eq!(request.take::<u8>(), Some(10));
+    assert_eq!(request.get::<u8>(), Some(&1));
+    assert_eq!(request.insert(4u32), Some(3));
+    request.top_mut().insert(String::from("top"));
+    assert_eq!(request.top().len(), 3);
+
+    assert_eq!(
+        format!("{request:?}"),
+        r#"LayeredTypeSet { top: TypeSet({"alloc::string::String", "bool", "u32"}), ancestors: [TypeSet({"&str", "u16"}), TypeSet({"&str", "u8"})] }"#
+    );
+
+    let top = request.into_top();
+    assert_eq!(top.get::<u32>(), Some(&4));
+}
+
+#[test(harness)]
+fn immutable() {
+    let base = ImmutableTypeSet::new()
+        .with("hello")
+        .with_captured(Capture::new(String::from("shared")).cloneable().debug());
+    let branch = base.with_captured(Capture::new(1usize).cloneable());
+    assert!(branch.shares::<String>(&base));
+    assert!(!branch.shares::<usize>(&base));
+    assert!(base.shares::<u8>(&branch));
+    assert_eq!(base.len(), 2);
+    assert_eq!(branch.len(), 3);
+    assert_eq!(
+        format!("{branch:?}"),
+        r#"ImmutableTypeSet({"&str", "alloc::string::String" => "shared", "usize"})"#
+    );
+
+    let pruned = branch.without::<&'static str>();
+    assert!(!pruned.contains::<&'static str>());
+    assert!(branch.contains::<&'static str>());
+
+    let branch: ImmutableTypeSet = TypeSet::try_from(branch).unwrap_err();
+    drop(base);
+    let mut set: TypeSet = branch.try_into().unwrap();
+    set.get_mut::<String>().unwrap().push('!');
+    assert_eq!(set.get::<String>().unwrap(), "shared!");
+    assert_eq!(pruned.get::<String>().unwrap(), "shared");
+
+    let immutable = ImmutableTypeSet::from(set);
+    assert_eq!(immutable.get::<usize>(), Some(&1));
+    assert!(!immutable.is_empty());
+
+    struct N<const I: usize>(usize);
+    let mut base = ImmutableTypeSet::new();
+    macro_rules! each {
+        ($($i:literal)*) => {
+            $(base = base.with(N::<$i>($i));)*
+        };
+    }
+    each!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15);
+
+    let a = base.with(N::<3>(30)).without::<N<7>>();
+    let b = base.with(N::<5>(50)).without::<N<12>>().without::<N<16>>();
+    assert_eq!((base.len(), a.len(), b.len()), (16, 15, 15));
+    assert!(a.shares::<N<0>>(&b) && a.shares::<N<9>>(&b) && a.shares::<N<15>>(&b));
+    assert!(!a.shares::<N<3>>(&b) && !a.shares::<N<5>>(&b) && !a.shares::<N<7>>(&b));
+    assert_eq!(a.get::<N<3>>().map(|n| n.0), Some(30));
+    assert_eq!(b.get::<N<3>>().map(|n| n.0), Some(3));
+    assert_eq!(base.get::<N<5>>().map(|n| n.0), Some(5));
+    assert!(!a.contains::<N<7>>() && b.contains::<N<7>>());
+    assert!(a.contains::<N<12>>() && !b.contains::<N<12>>());
+
+    let a = TypeSet::try_from(a).unwrap_err();
+    drop((base, b));
+    let set: TypeSet = a.try_into().unwrap();
+    assert_eq!(set.len(), 15);
+    assert_eq!(set.get::<N<15>>().map(|n| n.0), Some(15));
+}
+
+#[test(harness)]
+fn observe() {
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    let mut set = LocalTypeSet::default();
+    let subscription = set.observe::<String>({
+        let changes = Rc::clone(&changes);
+        move |old, new| {
+            changes.borrow_mut().push((old.cloned(), new.cloned()));
+        }
+    });
+    let count = Rc::new(Cell::new(0));
+    set.observe::<u8>({
+        let count = Rc::clone(&count);
+        move |_, _| count.set(count.get() + 1)
+    });
+
+    set.insert(String::from("a"));
+    set.entry::<String>().or_insert_with(|| unreachable!());
+    set.get_mut::<String>().unwrap().push('!');
+    if let Entry::Occupied(mut occupied) = set.entry::<String>() {
+        occupied.insert(String::from("b"));
+    }
+    set.insert_captured(Capture::new(String::from("c")).cloneable());
+    set.merge(LocalTypeSet::default().with(String::from("d")).with(1u8));
+    assert_eq!(set.take::<String>(), Some(String::from("d")));
+    set.entry::<String>().or_default();
+    assert!(set.entry::<String>().take().is_some());
+    set.retain(|_| false);
+
+    assert!(subscription.is_subscribed());
+    subscription.unsubscribe();
+    assert!(!subscription.is_subscribed());
+    set.insert(String::from("unobserved"));
+    set.insert(2u8);
+
+    let some = |s: &str| Some(String::from(s));
+    assert_eq!(
+        *changes.borrow(),
+        [
+            (None, some("a")),
+            (some("a!"), some("b")),
+            (some("b"), some("c")),
+            (some("c"), some("d")),
+            (some("d"), None),
+            (None, some("")),
+            (some(""), None),
+        ]
+    );
+    assert_eq!(count.get(), 2);
+}
+
+#[test(harness)]
+fn transaction() {
+    let mut set = TypeSet::new()
+        .with(String::from("hello"))
+        .with(1usize)
+        .with(auth::User(1));
+    let changes = Arc::new(Mutex::new(0));
+    set.observe::<usize>({
+        let changes = Arc::clone(&changes);
+        move |_, _| *changes.lock().unwrap() += 1
+    });
+
+    let result = set.transaction(|tx| {
+        tx.get_mut::<String>().unwrap().push_str(" world");
+        tx.get_mut::<String>().unwrap().push('!');
+        assert_eq!(tx.get::<String>().unwrap(), "hello world!");
+        assert_eq!(tx.take::<usize>(), Some(1));
+        tx.insert(2usize);
+        assert_eq!(tx.take::<usize>(), Some(2));
+        assert!(tx.remove::<auth::User>());
+        assert!(!tx.remove::<auth::User>());
+        tx.insert(8u8);
+        assert_eq!(tx.len(), 2);
+        Err("stage failed")
+    });
+    assert_eq!(result, Err::<(), _>("stage failed"));
+    assert_eq!(set.get::<String>().unwrap(), "hello");
+    assert_eq!(set.get::<usize>(), Some(&1));
+    assert_eq!(set.get::<auth::User>().unwrap().0, 1);
+    assert!(!set.contains::<u8>());
+    assert_eq!(*changes.lock().unwrap(), 4);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        set.transaction(|tx| {
+            tx.insert(3usize);
+            panic!("stage panicked");
+            #[allow(unreachable_code)]
+            Ok::<_, ()>(())
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(set.get::<usize>(), Some(&1));
+    // a rollback during a panic does not notify observers
+    assert_eq!(*changes.lock().unwrap(), 5);
+
+    let len = set
+        .transaction(|tx| {
+            tx.insert(4usize);
+            tx.remove::<String>();
+            Ok::<_, ()>(tx.len())
+        })
+        .unwrap();
+    assert_eq!(len, 2);
+    assert_eq!(set.get::<usize>(), Some(&4));
+    assert!(!set.contains::<String>());
+}
+
+#[test(harness)]
+fn dyn_casts() {
+    use std::{any::TypeId, fmt::Display};
+
+    trait Middleware {
+        fn name(&self) -> String;
+    }
+
+    struct Logger;
+    impl Middleware for Logger {
+        fn name(&self) -> String {
+            String::from("logger")
+        }
+    }
+
+    #[derive(Debug)]
+    struct Router(usize);
+    impl Middleware for Router {
+        fn name(&self) -> String {
+            format!("router with {} routes", self.0)
+        }
+    }
+    impl Display for Router {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Router({})", self.0)
+        }
+    }
+
+    let capture = Capture::new(Router(2))
+        .cast::<dyn Middleware>(|r| r)
+        .cast::<dyn Display>(|r| r);
+    assert!(format!("{capture:?}").contains(r#"Middleware", "dyn core::fmt::Display"]"#));
+
+    let mut set = TypeSet::new()
+        .with_captured(Capture::new(Logger).cast::<dyn Middleware>(|l| l))
+        .with_captured(capture)
+        .with(1usize);
+
+    let mut names = set
+        .iter_dyn::<dyn Middleware>()
+        .map(Middleware::name)
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["logger", "router with 2 routes"]);
+    assert_eq!(set.iter_dyn::<dyn Middleware>().rev().count(), 2);
+    assert_eq!(set.iter_dyn::<dyn Display>().count(), 1);
+
+    assert_eq!(
+        set.get_dyn::<dyn Display>(TypeId::of::<Router>())
+            .unwrap()
+            .to_string(),
+        "Router(2)"
+    );
+    assert!(set.get_dyn::<dyn Display>(TypeId::of::<Logger>()).is_none());
+    assert!(set.get_dyn::<dyn Display>(TypeId::of::<usize>()).is_none());
+    assert!(set.get_dyn::<dyn Display>(TypeId::of::<u8>()).is_none());
+
+    set.get_mut::<Router>().unwrap().0 = 3;
+    let count = set.transaction(|tx| {
+        tx.remove::<Logger>();
+        Ok::<_, ()>(tx.iter_dyn::<dyn Middleware>().count())
+    });
+    assert_eq!(count, Ok(1));
+    assert_eq!(
+        set.get_dyn::<dyn Middleware>(TypeId::of::<Router>())
+            .unwrap()
+            .name(),
+        "router with 3 routes"
+    );
+}
+
+#[test(harness)]
+fn keyed() {
+    use std::time::Duration;
+    use type_set::keyed::Key;
+
+    enum ConnectTimeout {}
+    impl Key for ConnectTimeout {
+        type Value = Duration;
+    }
+
+    enum ReadTimeout {}
+    impl Key for ReadTimeout {
+        type Value = Duration;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Retries(u8);
+    impl Key for Retries {
+        type Value = Self;
+    }
+
+    let mut set = TypeSet::new()
+        .with_key::<ConnectTimeout>(Duration::from_secs(5))
+        .with(Duration::from_secs(1))
+        .with(Retries(3));
+    assert_eq!(set.insert_key::<ReadTimeout>(Duration::from_secs(30)), None);
+    assert_eq!(set.len(), 4);
+    assert_eq!(set.get::<Duration>(), Some(&Duration::from_secs(1)));
+    assert_eq!(
+        set.get_key::<ConnectTimeout>(),
+        Some(&Duration::from_secs(5))
+    );
+    assert_eq!(set.get_key::<ReadTimeout>(), Some(&Duration::from_secs(30)));
+    assert_eq!(set.get_key::<Retries>(), Some(&Retries(3)));
+
+    *set.get_key_mut::<ReadTimeout>().unwrap() *= 2;
+    assert_eq!(set.get_key::<ReadTimeout>(), Some(&Duration::from_secs(60)));
+    *set.entry_key::<ConnectTimeout>().or_default() += Duration::from_secs(1);
+    assert_eq!(
+        set.get_key::<ConnectTimeout>(),
+        Some(&Duration::from_secs(6))
+    );
+    set.entry_key::<Retries>().into_mut().unwrap().0 += 1;
+    assert_eq!(set.get::<Retries>(), Some(&Retries(4)));
+
+    let set = set.drain().collect::<TypeSet>();
+    assert_eq!(
+        set.get_key::<ConnectTimeout>(),
+        Some(&Duration::from_secs(6))
+    );
+    assert_eq!(set.get::<Duration>(), Some(&Duration::from_secs(1)));
+
+    let mut names = [
+        std::any::type_name::<ConnectTimeout>(),
+        std::any::type_name::<Duration>(),
+        std::any::type_name::<ReadTimeout>(),
+        std::any::type_name::<Retries>(),
+    ];
+    names.sort_unstable();
+    assert_eq!(set.try_get::<u8>().unwrap_err().present_types(), names);
+    let debug = format!("{set:?}");
+    assert!(
+        debug.contains(std::any::type_name::<ConnectTimeout>())
+            && debug.contains(std::any::type_name::<ReadTimeout>()),
+        "{debug}"
+    );
+
+    let mut set = set;
+    assert_eq!(
+        set.take_key::<ConnectTimeout>(),
+        Some(Duration::from_secs(6))
+    );
+    assert!(!set.contains_key::<ConnectTimeout>());
+    assert!(set.contains_key::<ReadTimeout>());
+    assert!(set.contains::<Duration>());
+}
+
+#[test(harness)]
+fn multi() {
+    #[derive(Debug, PartialEq)]
+    struct Cookie(&'static str);
+
+    let mut set = TypeSet::new()
+        .with(Cookie("single"))
+        .with(vec![Cookie("vec")]);
+    assert!(set.get_all::<Cookie>().is_empty());
+    set.push(Cookie("a"));
+    set.push(Cookie("b"));
+    assert_eq!(set.get_all::<Cookie>(), [Cookie("a"), Cookie("b")]);
+    assert_eq!(set.get::<Cookie>(), Some(&Cookie("single")));
+    assert_eq!(set.get::<Vec<Cookie>>(), Some(&vec![Cookie("vec")]));
+
+    set.entry_all::<Cookie>()
+        .or_default()
+        .retain(|cookie| cookie.0 != "a");
+    assert_eq!(set.get_all::<Cookie>(), [Cookie("b")]);
+    assert!(matches!(set.entry_all::<u8>(), Entry::Vacant(_)));
+
+    assert_eq!(set.drain_all::<Cookie>().collect::<Vec<_>>(), [Cookie("b")]);
+    assert!(set.get_all::<Cookie>().is_empty());
+    assert_eq!(set.drain_all::<Cookie>().len(), 0);
+    assert_eq!(set.len(), 2);
+
+    let set = LocalTypeSet::default()
+        .with_pushed(Rc::new(1))
+        .with_pushed(Rc::new(2));
+    assert_eq!(set.get_all::<Rc<i32>>().len(), 2);
+}
+
+#[test(harness)]
+fn merge_with() {
+    use type_set::merge::{Merge, MergePolicy};
+
+    #[derive(Debug, PartialEq)]
+    struct Headers(Vec<&'static str>);
+    impl Merge for Headers {
+        fn merge(&mut self, incoming: Self) {
+            self.0.extend(incoming.0);
+        }
+    }
+
+    let incoming = || {
+        TypeSet::new()
+            .with(Headers(vec!["b"]))
+            .with(String::from(" world"))
+            .with(2u8)
+            .with(32u32)
+    };
+    let existing = || {
+        TypeSet::new()
+            .with(Headers(vec!["a"]))
+            .with(String::from("hello"))
+            .with(1u8)
+    };
+
+    let mut set = existing();
+    let report = set.merge_with(incoming(), MergePolicy::keep_existing());
+    assert_eq!(report.collisions().len(), 3);
+    assert_eq!(report.collisions()[0], "alloc::string::String");
+    assert!(report.collisions()[1].ends_with("::Headers"));
+    assert_eq!(report.collisions()[2], "u8");
+    assert!(!report.is_clean());
+    assert_eq!(set.get::<Headers>(), Some(&Headers(vec!["a"])));
+    assert_eq!(set.get::<u8>(), Some(&1));
+    assert_eq!(set.get::<u32>(), Some(&32));
+
+    let mut set = existing();
+    set.merge_with(incoming(), MergePolicy::take_incoming());
+    assert_eq!(set.get::<Headers>(), Some(&Headers(vec!["b"])));
+    assert_eq!(set.get::<String>().unwrap(), " world");
+
+    let mut seen = Vec::new();
+    let mut set = existing();
+    let replaced = Arc::new(Mutex::new(Vec::new()));
+    set.observe::<u8>({
+        let replaced = Arc::clone(&replaced);
+        move |old, new| replaced.lock().unwrap().push((old.copied(), new.copied()))
+    });
+    let report = set.merge_with(
+        incoming(),
+        MergePolicy::take_incoming()
+            .merge::<Headers>()
+            .merge::<String>()
+            .resolve(|existing: &mut u32, incoming| seen.push((*existing, incoming))),
+    );
+    assert_eq!(report.collisions().len(), 3);
+    assert_eq!(set.get::<Headers>(), Some(&Headers(vec!["a", "b"])));
+    assert_eq!(set.get::<String>().unwrap(), "hello world");
+    assert_eq!(set.get::<u8>(), Some(&2));
+    assert_eq!(*replaced.lock().unwrap(), [(Some(1), Some(2))]);
+    assert!(seen.is_empty());
+
+    assert!(TypeSet::new()
+        .merge_with(incoming(), MergePolicy::keep_existing())
+        .is_clean());
+}
+
+#[test(harness)]
+fn diff() {
+    let before = TypeSet::new()
+        .with_captured(Capture::new(String::from("same")).comparable())
+        .with_captured(Capture::new(1usize).comparable())
+        .with(auth::Token)
+        .with(1u8);
+    let mut after = TypeSet::new()
+        .with_captured(Capture::new(String::from("same")).comparable())
+        .with(2usize)
+        .with(auth::Token)
+        .with(1u16);
+
+    let diff = TypeSet::diff(&before, &after);
+    assert_eq!(diff.added(), ["u16"]);
+    assert_eq!(diff.removed(), ["u8"]);
+    assert_eq!(diff.changed(), ["usize"]);
+    assert_eq!(diff.unchecked().len(), 1);
+    assert!(diff.unchecked()[0].ends_with("auth::Token"));
+    assert!(!diff.is_empty());
+    assert!(diff.to_string().starts_with("+ u16\n- u8\n~ usize\n? "));
+    assert!(format!("{diff:?}").starts_with(
+        r#"Diff { added: ["u16"], removed: ["u8"], changed: ["usize"], unchecked: ["#
+    ));
+
+    after.insert(1usize);
+    after.insert(1u8);
+    after.take::<u16>();
+    after.take::<auth::Token>();
+    let diff = TypeSet::diff(&before, &after);
+    assert_eq!(diff.removed().len(), 1);
+    let diff = TypeSet::diff(&after, &after);
+    assert!(diff.is_empty());
+    assert_eq!(diff.unchecked(), ["u8", "usize"]);
+
+    assert_eq!(
+        TypeSet::diff(&TypeSet::new(), &TypeSet::new()).to_string(),
+        "no changes"
+    );
+}
+
+#[test(harness)]
+fn eq_and_hash() {
+    use std::{
+        collections::HashSet,
+        hash::{DefaultHasher, Hasher},
+    };
+
+    let fixture = || {
+        TypeSet::new()
+            .with_comparable(String::from("hello"))
+            .with_captured(Capture::new(1usize).comparable().hashable())
+    };
+    assert_eq!(fixture(), fixture());
+    assert_ne!(fixture(), fixture().with_comparable(2u8));
+    assert_ne!(fixture(), fixture().with_comparable(2usize));
+    assert_eq!(TypeSet::new(), TypeSet::new());
+
+    let mixed = fixture().with(String::from("hello"));
+    assert_eq!(mixed, fixture());
+    let error = fixture()
+        .with(MyCustomStruct)
+        .try_eq(&fixture().with(MyCustomStruct))
+        .unwrap_err();
+    assert_eq!(error.type_names(), ["tests::MyCustomStruct"]);
+    assert_eq!(
+        error.to_string(),
+        "could not compare type-sets because these types were not inserted as comparable: tests::MyCustomStruct"
+    );
+    assert_eq!(
+        *catch_unwind(|| fixture().with(MyCustomStruct) == fixture().with(MyCustomStruct))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap(),
+        error.to_string()
+    );
+
+    let hashable = |n: usize| {
+        TypeSet::new()
+            .with_captured(Capture::new(n).hashable().comparable())
+            .with_captured(Capture::new("hi").hashable().comparable())
+    };
+    let hash = |set: TypeSet| {
+        let mut state = DefaultHasher::new();
+        set.try_hash(&mut state).unwrap();
+        state.finish()
+    };
+    assert_eq!(hash(hashable(1)), hash(hashable(1)));
+    assert_ne!(hash(hashable(1)), hash(hashable(2)));
+    let mut state = DefaultHasher::new();
+    let error = fixture().try_hash(&mut state).unwrap_err();
+    assert_eq!(error.type_names(), ["alloc::string::String"]);
+    assert_eq!(state.finish(), DefaultHasher::new().finish());
+
+    let set = [hashable(1), hashable(2), hashable(1)]
+        .into_iter()
+        .map(hash)
+        .collect::<HashSet<_>>();
+    assert_eq!(set.len(), 2);
+}
+
+#[test(harness)]
+fn missing_type() {
+    use std::any::TypeId;
+
+    let mut set = TypeSet::new().with(1usize).with("hello");
+    *set.try_get_mut::<usize>().unwrap() += 1;
+    assert_eq!(set.try_get::<usize>(), Ok(&2));
+    assert_eq!(set.try_take::<usize>(), Ok(2));
+
+    let error = set.try_take::<usize>().unwrap_err();
+    assert_eq!(error.type_name(), "usize");
+    assert_eq!(error.type_id(), TypeId::of::<usize>());
+    assert_eq!(error.present_types(), ["&str"]);
+    assert_eq!(
+        error.to_string(),
+        "no usize in type-set, which contains: &str"
+    );
+
+    let error = set.try_get_mut::<String>().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "no alloc::string::String in type-set, which contains: &str (did you mean &str?)"
+    );
+    assert_eq!(error.suggestions(), ["&str"]);
+
+    let error: Box<dyn std::error::Error> = Box::new(TypeSet::new().try_get::<u8>().unwrap_err());
+    assert_eq!(error.to_string(), "no u8 in empty type-set");
+}
+
+#[test(harness)]
+fn suggestions() {
+    mod other {
+        pub struct Token;
+        pub struct Vec<T>(pub T);
+    }
+
+    let set = TypeSet::new()
+        .with(Arc::new(auth::Token))
+        .with(other::Token)
+        .with(Box::new(String::from("boxed")))
+        .with(Some(Arc::new(Box::new(1usize))))
+        .with(std::collections::HashMap::<u8, u8>::new())
+        .with(vec![1u8])
+        .with(1u8);
+
+    assert_eq!(
+        set.suggestions::<auth::Token>(),
+        [
+            "alloc::sync::Arc<tests::auth::Token>",
+            "tests::suggestions::suggestions::other::Token"
+        ]
+    );
+    assert_eq!(
+        set.suggestions::<&'static str>(),
+        ["alloc::boxed::Box<alloc::string::String>"]
+    );
+    assert_eq!(
+        set.suggestions::<usize>(),
+        ["core::option::Option<alloc::sync::Arc<alloc::boxed::Box<usize>>>"]
+    );
+    assert!(set.suggestions::<u16>().is_empty());
+    assert!(set.suggestions::<u8>().is_empty());
+    assert!(set
+        .suggestions::<std::collections::HashMap<u8, u16>>()
+        .is_empty());
+    assert!(set.suggestions::<Vec<String>>().is_empty());
+    assert_eq!(set.suggestions::<other::Vec<u8>>(), ["alloc::vec::Vec<u8>"]);
+
+    let error = set.try_get::<Mutex<usize>>().unwrap_err();
+    assert_eq!(error.suggestions().len(), 1);
+    assert!(error.to_string().ends_with(
+        "(did you mean core::option::Option<alloc::sync::Arc<alloc::boxed::Box<usize>>>?)"
+    ));
+}
+
+#[test(harness)]
+fn ambiguity() {
+    fn get<T: Send + Sync + 'static>(set: &TypeSet, _: &T) -> bool {
+        set.get::<T>().is_some()
+    }
+
+    fn probe<T: Send + Sync + 'static>(set: &mut TypeSet, _: &T) -> String {
+        assert!(!set.contains::<T>());
+        assert!(set.get_mut::<T>().is_none());
+        assert!(set.take::<T>().is_none());
+        set.try_get_mut::<T>().err().unwrap().to_string()
+    }
+
+    // two closures have different types with the same name
+    let (a, b) = (|| 1u8, || 2u8);
+    let name = std::any::type_name_of_val(&a);
+    assert_eq!(name, std::any::type_name_of_val(&b));
+
+    // the hook is global, so only record warnings for the closures in this test
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    type_set::ambiguity::set_hook({
+        let warnings = Arc::clone(&warnings);
+        move |warning| {
+            if warning == name {
+                warnings.lock().unwrap().push(warning);
+            }
+        }
+    });
+
+    let mut set = TypeSet::new().with(a).with(1usize);
+    assert!(set.ambiguous_types().is_empty());
+    assert!(get(&set, &a));
+    assert!(!get(&set, &b));
+    assert!(set.get::<u8>().is_none());
+    assert_eq!(
+        probe(&mut set, &b),
+        format!(
+            "no {name} in type-set, which contains: {name}, usize (a different type with the \
+             same name is present, so there may be more than one version of its crate)"
+        )
+    );
+    assert_eq!(*warnings.lock().unwrap(), [name; 5]);
+
+    // keyed and multi values are stored under another key, but are not a different type
+    enum Timeout {}
+    impl type_set::keyed::Key for Timeout {
+        type Value = std::time::Duration;
+    }
+    let mut values = TypeSet::new().with_key::<Timeout>(std::time::Duration::ZERO);
+    values.push(1u8);
+    assert!(!values.contains::<std::time::Duration>());
+    assert!(values.get::<Vec<u8>>().is_none());
+    let message = values
+        .try_get::<std::time::Duration>()
+        .unwrap_err()
+        .to_string();
+    assert!(!message.contains("same name"), "{message}");
+    assert_eq!(warnings.lock().unwrap().len(), 5);
+
+    set.insert(b);
+    assert_eq!(set.ambiguous_types(), [name]);
+    assert!(get(&set, &b));
+    type_set::ambiguity::clear_hook();
+    set.retain(|erased| erased.type_name() != name);
+    assert!(!get(&set, &b));
+    assert_eq!(warnings.lock().unwrap().len(), 5);
+    assert!(set.ambiguous_types().is_empty());
+}
+
+#[cfg(feature = "provenance")]
+#[test(harness)]
+fn provenance() {
+    let mut set = TypeSet::new().with(1usize);
+    let with_line = line!() - 1;
+    assert_eq!(set.inserted_at::<usize>().unwrap().file(), file!());
+    assert_eq!(set.inserted_at::<usize>().unwrap().line(), with_line);
+    assert!(set.inserted_at::<u8>().is_none());
+
+    set.entry::<u8>().or_insert(8);
+    assert_eq!(set.inserted_at::<u8>().unwrap().line(), line!() - 1);
+    set.get_or_insert_with(String::new);
+    assert_eq!(set.inserted_at::<String>().unwrap().line(), line!() - 1);
+    *set.get_mut::<usize>().unwrap() += 1;
+    assert_eq!(set.inserted_at::<usize>().unwrap().line(), with_line);
+    set.insert_debug(2usize);
+    let location = set.inserted_at::<usize>().unwrap();
+    assert_eq!(location.line(), line!() - 2);
+    assert!(format!("{set:#?}").contains(&format!("\"usize\" @ {location} => 2")));
+    assert!(!format!("{set:?}").contains(" @ "));
+
+    let set = SyncTypeSet::new();
+    set.insert(1usize);
+    let with_line = line!() - 1;
+    let set = set.into_inner();
+    assert_eq!(set.inserted_at::<usize>().unwrap().line(), with_line);
+}
+
+#[test(harness)]
+fn audit() {
+    use type_set::audit::{Access, Counts};
+
+    let mut set = TypeSet::new().with(1usize);
+    assert!(!set.is_auditing());
+    assert_eq!(set.get::<usize>(), Some(&1));
+    assert!(set.access_report().is_empty());
+    assert_eq!(set.access_report().to_string(), "no accesses");
+
+    set.enable_auditing();
+    assert!(set.is_auditing());
+    set.insert(2u8);
+    assert!(set.access_report().is_empty());
+
+    assert!(set.contains::<usize>());
+    assert!(!set.contains::<String>());
+    assert_eq!(set.get::<usize>(), Some(&1));
+    set.try_get::<String>().unwrap_err();
+    *set.get_mut::<usize>().unwrap() += 1;
+    assert!(set.try_get_mut::<String>().is_err());
+    assert_eq!(set.take::<u8>(), Some(2));
+    assert_eq!(set.try_take::<u8>().ok(), None);
+    set.entry::<String>().or_default();
+    set.get_or_insert_with(String::new);
+
+    let report = set.access_report();
+    let of = |counts: Counts| (counts.hits(), counts.misses());
+    assert_eq!(of(report.counts::<usize>(Access::Contains)), (1, 0));
+    assert_eq!(of(report.counts::<String>(Access::Contains)), (0, 1));
+    assert_eq!(of(report.counts::<usize>(Access::Get)), (1, 0));
+    assert_eq!(of(report.counts::<String>(Access::Get)), (0, 1));
+    assert_eq!(of(report.counts::<usize>(Access::GetMut)), (1, 0));
+    assert_eq!(of(report.counts::<String>(Access::GetMut)), (0, 1));
+    assert_eq!(of(report.counts::<u8>(Access::Take)), (1, 1));
+    assert_eq!(of(report.counts::<String>(Access::Entry)), (1, 1));
+    assert_eq!(of(report.counts::<u8>(Access::Entry)), (0, 0));
+    assert_eq!(report.iter().count(), 8);
+    assert_eq!(
+        report.to_string(),
+        "alloc::string::String contains: 0 hits, 1 misses\n\
+         alloc::string::String get: 0 hits, 1 misses\n\
+         alloc::string::String get_mut: 0 hits, 1 misses\n\
+         alloc::string::String entry: 1 hits, 1 misses\n\
+         u8 take: 1 hits, 1 misses\n\
+         usize contains: 1 hits, 0 misses\n\
+         usize get: 1 hits, 0 misses\n\
+         usize get_mut: 1 hits, 0 misses"
+    );
+
+    let (matching, _) = set.partition(|erased| erased.type_name() == "usize");
+    assert!(!matching.is_auditing());
+
+    let parent = TypeSet::new().with(1usize).with_auditing();
+    let layered = TypeSet::new().with_auditing().with_parent(&parent);
+    assert_eq!(layered.get::<usize>(), Some(&1));
+    assert_eq!(layered.layer_of::<usize>(), Some(1));
+    assert!(!layered.contains::<u8>());
+    let report = layered.top().access_report();
+    assert_eq!(of(report.counts::<usize>(Access::Get)), (1, 0));
+    assert_eq!(of(report.counts::<usize>(Access::Contains)), (1, 0));
+    assert_eq!(of(report.counts::<u8>(Access::Contains)), (0, 1));
+    assert!(parent.access_report().is_empty());
+
+    enum Timeout {}
+    impl type_set::keyed::Key for Timeout {
+        type Value = std::time::Duration;
+    }
+    let mut set = TypeSet::new().with_auditing();
+    set.insert_key::<Timeout>(std::time::Duration::ZERO);
+    set.push(1u8);
+    assert!(set.contains_key::<Timeout>());
+    assert!(set.get_key::<Timeout>().is_some());
+    assert!(set.get_key_mut::<Timeout>().is_some());
+    assert!(set.take_key::<Timeout>().is_some());
+    set.entry_key::<Timeout>().or_default();
+    assert_eq!(set.get_all::<u8>(), [1]);
+    assert!(set.get_all::<u16>().is_empty());
+    assert_eq!(set.drain_all::<u8>().len(), 1);
+    let report = set.access_report();
+    assert_eq!(of(report.counts_key::<Timeout>(Access::Contains)), (1, 0));
+    assert_eq!(of(report.counts_key::<Timeout>(Access::Get)), (1, 0));
+    assert_eq!(of(report.counts_key::<Timeout>(Access::GetMut)), (1, 0));
+    assert_eq!(of(report.counts_key::<Timeout>(Access::Take)), (1, 0));
+    assert_eq!(of(report.counts_key::<Timeout>(Access::Entry)), (0, 1));
+    assert_eq!(of(report.counts_all::<u8>(Access::Get)), (1, 0));
+    assert_eq!(of(report.counts_all::<u16>(Access::Get)), (0, 1));
+    assert_eq!(of(report.counts_all::<u8>(Access::Take)), (1, 0));
+    assert_eq!(of(report.counts_all::<u8>(Access::Entry)), (0, 0));
+    assert_eq!(report.iter().count(), 8);
+}
+
+#[test(harness)]
+fn once() {
+    use type_set::{storage::Local, OnceTypeSet};
+
+    let set = OnceTypeSet::new();
+    assert!(set.is_empty());
+    assert_eq!(set.get::<usize>(), None);
+    assert_eq!(set.set(1usize), Ok(()));
+    assert_eq!(set.set(2usize), Err(2));
+    assert_eq!(*set.get_or_init(|| 3usize), 1);
+    assert!(set.contains::<usize>());
+
+    let string = set.get_or_init(|| {
+        assert_eq!(set.set(String::from("first")), Ok(()));
+        String::from("second")
+    });
+    assert_eq!(string, "first");
+    let greeting =
+        set.get_or_init(|| Box::<str>::from(format!("{}!", set.get_or_init(|| "hello"))));
+    assert_eq!(&**greeting, "hello!");
+    assert_eq!(set.len(), 4);
+    assert_eq!(
+        format!("{set:?}"),
+        r#"OnceTypeSet({"&str", "alloc::boxed::Box<str>", "alloc::string::String", "usize"})"#
+    );
+
+    struct N<const I: usize>;
+    let set = OnceTypeSet::new();
+    let calls = Mutex::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                macro_rules! init {
+                    ($($i:literal)*) => {$(
+                        set.get_or_init(|| {
+                            *calls.lock().unwrap() += 1;
+                            N::<$i>
+                        });
+                    )*};
+                }
+                init!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31);
+            });
+        }
+    });
+    assert_eq!(set.len(), 32);
+    assert!(*calls.lock().unwrap() >= 32);
+    assert!(set.contains::<N<0>>() && set.contains::<N<31>>());
+
+    let mut set = OnceTypeSet::from(TypeSet::new().with(1u8));
+    *set.get_mut::<u8>().unwrap() += 1;
+    assert_eq!(set.get_mut::<u16>(), None);
+    let set = set.into_inner();
+    assert_eq!(set.get::<u8>(), Some(&2));
+
+    let shared = Rc::new(Cell::new(0));
+    let set = OnceTypeSet::<Local>::default();
+    set.get_or_init(|| Rc::clone(&shared)).set(1);
+    assert_eq!(shared.get(), 1);
+}
+
+#[test(harness)]
+fn providers() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use type_set::providers::{Providers, ResolveError};
+
+    struct Config(&'static str);
+    struct Pool(String);
+    struct Service(String);
+    struct Unprovided;
+    struct Cyclic;
+    struct Loop(u8);
+
+    let builds = Arc::new(AtomicUsize::new(0));
+    let providers = Providers::new()
+        .with::<_, ()>(|()| Config("db://"))
+        .with::<_, (Config,)>({
+            let builds = Arc::clone(&builds);
+            move |(config,)| {
+                builds.fetch_add(1, Ordering::Relaxed);
+                Pool(format!("pool for {}", config.0))
+            }
+        })
+        .with::<_, (Pool, Config)>(|(pool, config)| Service(format!("{} at {}", pool.0, config.0)))
+        .with::<_, (Unprovided,)>(|_| 1u8)
+        .with::<_, (Loop,)>(|_| Cyclic)
+        .with::<_, (Cyclic,)>(|_| String::new())
+        .with::<_, (String,)>(|_| Loop(0));
+
+    assert!(providers.provides::<Pool>());
+    assert!(!providers.provides::<Unprovided>());
+
+    let mut set = TypeSet::new();
+    assert_eq!(
+        providers.resolve::<Service>(&mut set).unwrap().0,
+        "pool for db:// at db://"
+    );
+    assert_eq!(set.len(), 3);
+    providers.resolve::<Service>(&mut set).unwrap();
+    providers.resolve::<Pool>(&mut set).unwrap();
+    assert_eq!(builds.load(Ordering::Relaxed), 1);
+
+    let mut set = TypeSet::new()
+        .with(Config("other://"))
+        .with(Pool("existing".into()));
+    assert_eq!(
+        providers.resolve::<Service>(&mut set).unwrap().0,
+        "existing at other://"
+    );
+    assert_eq!(builds.load(Ordering::Relaxed), 1);
+
+    let error = providers.resolve::<u8>(&mut set).err().unwrap();
+    assert!(matches!(error, ResolveError::Missing(_)));
+    assert_eq!(error.type_names().len(), 2);
+    assert!(error.to_string().starts_with("could not resolve tests::"));
+    assert!(error.to_string().ends_with("(required by u8)"));
+
+    let error = providers.resolve::<Loop>(&mut set).err().unwrap();
+    let names = error.type_names();
+    assert!(matches!(error, ResolveError::Cycle(_)));
+    assert_eq!(names.len(), 4);
+    assert!(names[0].ends_with("::Loop") && names[3].ends_with("::Loop"));
+    assert_eq!(names[1], "alloc::string::String");
+    assert!(names[2].ends_with("::Cyclic"));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "could not resolve type-set providers because of a dependency cycle: {}",
+            names.join(" -> ")
+        )
+    );
+    assert!(!set.contains::<Loop>());
+
+    let mut set = TypeSet::new().with(String::from("breaks the cycle"));
+    assert_eq!(providers.resolve::<Loop>(&mut set).unwrap().0, 0);
+
+    let debug = format!("{:?}", Providers::new().with::<_, (u8, u16)>(|_| 1u32));
+    assert_eq!(debug, r#"Providers({"u32": ["u8", "u16"]})"#);
+
+    let panic = catch_unwind(AssertUnwindSafe(|| {
+        Providers::new()
+            .with::<_, ()>(|()| 1u8)
+            .with::<_, ()>(|()| 2u8)
+    }));
+    assert!(panic.is_err());
+
+    let mut set = LocalTypeSet::default().with("local");
+    let providers =
+        Providers::default().with::<_, (&'static str,)>(|(name,)| Rc::<str>::from(*name));
+    assert_eq!(&**providers.resolve::<Rc<str>>(&mut set).unwrap(), "local");
+
+    // providers for a LocalTypeSet can capture values that are not Send or Sync
+    let counter = Rc::new(Cell::new(0u8));
+    let providers = Providers::default().with::<_, ()>({
+        let counter = Rc::clone(&counter);
+        move |()| {
+            counter.set(counter.get() + 1);
+            Rc::clone(&counter)
+        }
+    });
+    let mut set = LocalTypeSet::default();
+    let resolved = providers.resolve::<Rc<Cell<u8>>>(&mut set).unwrap();
+    assert!(Rc::ptr_eq(resolved, &counter));
+    providers.resolve::<Rc<Cell<u8>>>(&mut set).unwrap();
+    assert_eq!(counter.get(), 1);
+}